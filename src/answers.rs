@@ -0,0 +1,120 @@
+//! Answers from `cargo setup --interactive`, saved so the same crate can be
+//! scaffolded again with `cargo setup --answers <file>`.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ci::CiProvider;
+use crate::error::{Error, Result};
+use crate::preset::Preset;
+use crate::profile::{check, check_keywords, elements, parsed};
+use crate::scaffold::Kind;
+use crate::skeleton;
+
+/// The file the wizard writes into the new crate.
+pub const ANSWERS_FILE: &str = ".cargo-setup-answers.toml";
+
+/// What the wizard asks for. Flags given alongside `--answers` win over
+/// the file.
+///
+/// ```toml
+/// name = "my-tool"
+/// kind = "bin"
+/// license = "MIT OR Apache-2.0"
+/// ci = "gitlab"
+/// description = "Does one thing well"
+/// keywords = ["cli"]
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Answers {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Kind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<Preset>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ci: Option<CiProvider>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<Vec<String>>,
+}
+
+impl Answers {
+    /// Read the answers file at `path`, checking every answer in it.
+    pub fn load(path: &Path) -> Result<Self> {
+        let invalid = |problems: Vec<String>| Error::Answers {
+            path: path.to_path_buf(),
+            problems,
+        };
+        let contents = fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+        let answers: Answers =
+            toml::from_str(&contents).map_err(|err| invalid(vec![err.to_string()]))?;
+        let mut problems = Vec::new();
+        if let Some(name) = &answers.name {
+            problems.extend(Self::check("name", name).err());
+        }
+        if let Some(license) = &answers.license {
+            problems.extend(Self::check("license", license).err());
+        }
+        if let (Some(kind), Some(preset)) = (answers.kind, answers.preset) {
+            if kind != preset.kind() {
+                problems.push(format!(
+                    "`kind` is {}, but the {} preset makes a {} crate; drop one of them",
+                    kind.as_str(),
+                    preset,
+                    preset.kind().as_str()
+                ));
+            }
+        }
+        if let Some(keywords) = &answers.keywords {
+            let keywords: Vec<&str> = keywords.iter().map(String::as_str).collect();
+            problems.extend(check_keywords(&keywords).err());
+        }
+        if problems.is_empty() {
+            Ok(answers)
+        } else {
+            Err(invalid(problems))
+        }
+    }
+
+    /// Write the answers to `path`, with a note on how to replay them.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let contents = format!(
+            "# Answers from `cargo setup --interactive`; replay them with\n\
+             # `cargo setup --answers {}`\n{}",
+            ANSWERS_FILE,
+            toml::to_string(self).expect("answers are always representable as TOML")
+        );
+        fs::write(path, contents).map_err(|err| Error::io(path, err))
+    }
+
+    /// Check one answer as typed at the wizard's prompt, returning why it
+    /// is rejected. `preset` also takes `none`, and `keywords` are
+    /// comma-separated.
+    pub fn check(key: &str, value: &str) -> std::result::Result<(), String> {
+        match key {
+            // A path such as `crates/foo`, as on the command line
+            "name" => Path::new(value)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or_else(|| "`name`: the path doesn't end in a directory name".to_string())
+                .and_then(|name| {
+                    skeleton::validate_name(name).map_err(|reason| format!("`name`: {}", reason))
+                }),
+            "kind" => parsed::<Kind>(key, value),
+            "preset" if value == "none" => Ok(()),
+            "preset" => parsed::<Preset>(key, value),
+            "ci" => parsed::<CiProvider>(key, value),
+            "keywords" => check_keywords(&elements(value).collect::<Vec<_>>()),
+            "license" => check(key, value),
+            _ => Ok(()),
+        }
+    }
+}
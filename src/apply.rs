@@ -0,0 +1,52 @@
+//! Retrofit support: deciding which pieces an existing crate already has.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Files whose presence under any name variant (`README`, `README.md`,
+/// `LICENSE-MIT`, ...) means the crate already has that piece.
+const PREFIXES: &[&str] = &["README", "LICENSE", "CHANGELOG"];
+
+/// Directories that count as present as soon as they exist, whatever
+/// files they hold.
+const DIRECTORIES: &[&str] = &[
+    "tests",
+    "benches",
+    ".github/workflows",
+    ".forgejo/workflows",
+];
+
+/// If the crate at `root` already has the piece that the generated file
+/// `path` belongs to, return what was found.
+///
+/// `tests/basic.rs` is not added when the crate already has a `tests/`
+/// directory, `LICENSE-MIT` is not added next to an existing `LICENSE`, and
+/// so on. Any other file only counts as present if it exists itself.
+pub fn existing(root: &Path, path: &str) -> Option<PathBuf> {
+    if let Some(dir) = DIRECTORIES
+        .iter()
+        .find(|dir| path.starts_with(&format!("{}/", dir)))
+    {
+        let dir = root.join(dir);
+        return dir.is_dir().then_some(dir);
+    }
+
+    if !path.contains('/') {
+        if let Some(prefix) = PREFIXES
+            .iter()
+            .find(|prefix| path.to_ascii_uppercase().starts_with(*prefix))
+        {
+            let found = fs::read_dir(root).ok()?.find_map(|entry| {
+                let entry = entry.ok()?;
+                let name = entry.file_name().to_string_lossy().to_ascii_uppercase();
+                (name.starts_with(prefix) && entry.path().is_file()).then(|| entry.path())
+            });
+            if found.is_some() {
+                return found;
+            }
+        }
+    }
+
+    let file = root.join(path);
+    file.exists().then_some(file)
+}
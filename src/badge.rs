@@ -0,0 +1,77 @@
+//! Status badges at the top of the generated README.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// A README badge. New crates get the CI badge unless the profile or
+/// `--badges` lists others.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Badge {
+    /// The pipeline status, from the CI provider.
+    Ci,
+    /// The latest version on crates.io.
+    CratesIo,
+    /// The documentation on docs.rs.
+    DocsRs,
+    /// The license, linking to the LICENSE file.
+    License,
+}
+
+impl Badge {
+    pub const ALL: [Badge; 4] = [Badge::Ci, Badge::CratesIo, Badge::DocsRs, Badge::License];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Badge::Ci => "ci",
+            Badge::CratesIo => "crates-io",
+            Badge::DocsRs => "docs-rs",
+            Badge::License => "license",
+        }
+    }
+
+    /// The Markdown for the crate `name` under `license`. `ci` is the CI
+    /// provider's badge, which there may not be.
+    pub fn markdown(self, name: &str, license: &str, ci: Option<&str>) -> Option<String> {
+        match self {
+            Badge::Ci => ci.map(str::to_string),
+            Badge::CratesIo => Some(format!(
+                "[![Crates.io](https://img.shields.io/crates/v/{0}.svg)](https://crates.io/crates/{0})",
+                name
+            )),
+            Badge::DocsRs => Some(format!(
+                "[![Docs.rs](https://docs.rs/{0}/badge.svg)](https://docs.rs/{0})",
+                name
+            )),
+            // shields.io escapes `-` as `--`, and spaces as `%20`
+            Badge::License => Some(format!(
+                "[![License](https://img.shields.io/badge/license-{}-blue.svg)](LICENSE)",
+                license.replace('-', "--").replace(' ', "%20")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Badge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Badge {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Badge::ALL
+            .into_iter()
+            .find(|badge| badge.as_str() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown badge `{}` (expected ci, crates-io, docs-rs or license)",
+                    s
+                )
+            })
+    }
+}
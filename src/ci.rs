@@ -0,0 +1,1397 @@
+//! CI pipeline generation for the supported providers, all running the
+//! same build, test, fmt and clippy steps, assembled from a [`Matrix`] of
+//! OSes, toolchains, MSRV and targets.
+
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::license::Expression;
+use crate::template::Template;
+
+/// Where the generated pipeline runs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CiProvider {
+    /// GitHub Actions, `.github/workflows/ci.yml`.
+    #[default]
+    Github,
+    /// GitLab CI/CD, `.gitlab-ci.yml`.
+    Gitlab,
+    /// Forgejo or Gitea Actions, `.forgejo/workflows/ci.yml`.
+    #[serde(alias = "gitea")]
+    Forgejo,
+    /// Woodpecker CI, `.woodpecker.yml`.
+    Woodpecker,
+    /// No pipeline.
+    None,
+}
+
+impl CiProvider {
+    pub const ALL: [CiProvider; 5] = [
+        CiProvider::Github,
+        CiProvider::Gitlab,
+        CiProvider::Forgejo,
+        CiProvider::Woodpecker,
+        CiProvider::None,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CiProvider::Github => "github",
+            CiProvider::Gitlab => "gitlab",
+            CiProvider::Forgejo => "forgejo",
+            CiProvider::Woodpecker => "woodpecker",
+            CiProvider::None => "none",
+        }
+    }
+
+    /// The pipeline file, relative to the crate root.
+    pub fn path(self) -> Option<&'static str> {
+        match self {
+            CiProvider::Github => Some(".github/workflows/ci.yml"),
+            CiProvider::Gitlab => Some(".gitlab-ci.yml"),
+            CiProvider::Forgejo => Some(".forgejo/workflows/ci.yml"),
+            CiProvider::Woodpecker => Some(".woodpecker.yml"),
+            CiProvider::None => None,
+        }
+    }
+
+    /// Where repositories live when the profile doesn't say: GitHub for
+    /// GitHub Actions, gitlab.com for GitLab, Codeberg for the others.
+    pub fn default_host(self) -> &'static str {
+        match self {
+            CiProvider::Github | CiProvider::None => "https://github.com",
+            CiProvider::Gitlab => "https://gitlab.com",
+            CiProvider::Forgejo | CiProvider::Woodpecker => "https://codeberg.org",
+        }
+    }
+
+    /// The Markdown status badge for the repository at `repository`.
+    /// Woodpecker badges are served by the CI server at `woodpecker`.
+    pub fn badge(self, repository: &str, woodpecker: &str) -> Option<String> {
+        let (image, link) = match self {
+            CiProvider::Github => (
+                format!("{}/actions/workflows/ci.yml/badge.svg", repository),
+                format!("{}/actions", repository),
+            ),
+            CiProvider::Gitlab => (
+                format!("{}/badges/main/pipeline.svg", repository),
+                format!("{}/-/pipelines", repository),
+            ),
+            CiProvider::Forgejo => (
+                format!("{}/badges/workflows/ci.yml/badge.svg", repository),
+                format!("{}/actions", repository),
+            ),
+            CiProvider::Woodpecker => {
+                // `owner/name`, the end of the repository URL
+                let mut parts = repository.trim_end_matches('/').rsplitn(3, '/');
+                let name = parts.next().unwrap_or_default();
+                let slug = format!("{}/{}", parts.next().unwrap_or_default(), name);
+                (
+                    format!("{}/api/badges/{}/status.svg", woodpecker, slug),
+                    format!("{}/repos/{}", woodpecker, slug),
+                )
+            }
+            CiProvider::None => return None,
+        };
+        Some(format!("[![CI]({})]({})", image, link))
+    }
+}
+
+impl FromStr for CiProvider {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gitea" => Ok(CiProvider::Forgejo),
+            _ => CiProvider::ALL
+                .into_iter()
+                .find(|provider| provider.as_str() == s)
+                .ok_or_else(|| {
+                    format!(
+                        "unknown CI provider `{}` (expected github, gitlab, forgejo, woodpecker or none)",
+                        s
+                    )
+                }),
+        }
+    }
+}
+
+/// A Rust release channel to test on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Toolchain {
+    Stable,
+    Beta,
+    Nightly,
+}
+
+impl Toolchain {
+    pub const ALL: [Toolchain; 3] = [Toolchain::Stable, Toolchain::Beta, Toolchain::Nightly];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Toolchain::Stable => "stable",
+            Toolchain::Beta => "beta",
+            Toolchain::Nightly => "nightly",
+        }
+    }
+}
+
+impl fmt::Display for Toolchain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Toolchain {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Toolchain::ALL
+            .into_iter()
+            .find(|toolchain| toolchain.as_str() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown toolchain `{}` (expected stable, beta or nightly)",
+                    s
+                )
+            })
+    }
+}
+
+/// A minimum supported Rust version such as `1.74`, as written to
+/// `rust-version` in Cargo.toml.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RustVersion(String);
+
+impl RustVersion {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether this is an earlier release than `version`, e.g. `1.74` is
+    /// older than `1.85` but `1.85.1` isn't.
+    pub fn is_older_than(&self, version: &str) -> bool {
+        let parts = |version: &str| -> Vec<u64> {
+            let mut parts: Vec<u64> = version.split('.').filter_map(|p| p.parse().ok()).collect();
+            parts.resize(3, 0);
+            parts
+        };
+        parts(&self.0) < parts(version)
+    }
+}
+
+impl fmt::Display for RustVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for RustVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split('.').collect();
+        let valid = (2..=3).contains(&parts.len())
+            && parts
+                .iter()
+                .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()));
+        if valid {
+            Ok(RustVersion(s.to_string()))
+        } else {
+            Err(format!(
+                "invalid Rust version `{}` (expected e.g. 1.74 or 1.74.1)",
+                s
+            ))
+        }
+    }
+}
+
+impl From<RustVersion> for String {
+    fn from(version: RustVersion) -> Self {
+        version.0
+    }
+}
+
+impl TryFrom<String> for RustVersion {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// A job added next to build, test, fmt and clippy on request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Job {
+    /// Test coverage with cargo-llvm-cov, uploaded as an lcov report.
+    Coverage,
+    /// cargo-deny, with a `deny.toml` allowing the crate's license.
+    Deny,
+    /// A weekly cargo-audit run.
+    Audit,
+    /// The tests under miri, on nightly.
+    Miri,
+    /// cargo-semver-checks against the last release, for libraries. It
+    /// passes until there is a release on crates.io to compare with.
+    Semver,
+    /// `cargo doc` with warnings denied.
+    Docs,
+}
+
+impl Job {
+    pub const ALL: [Job; 6] = [
+        Job::Coverage,
+        Job::Deny,
+        Job::Audit,
+        Job::Miri,
+        Job::Semver,
+        Job::Docs,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Job::Coverage => "coverage",
+            Job::Deny => "deny",
+            Job::Audit => "audit",
+            Job::Miri => "miri",
+            Job::Semver => "semver",
+            Job::Docs => "docs",
+        }
+    }
+
+    /// How to run the job, on `primary` unless it needs a specific
+    /// toolchain.
+    fn spec(self, primary: Toolchain) -> JobSpec {
+        let job = JobSpec::new(self.as_str(), primary);
+        match self {
+            Job::Coverage => JobSpec {
+                name: "Coverage",
+                components: &["llvm-tools-preview"],
+                tool: Some("cargo-llvm-cov"),
+                step: Step::new(
+                    "Measure coverage",
+                    &["cargo llvm-cov --lcov --output-path lcov.info"],
+                ),
+                artifact: Some("lcov.info"),
+                ..job
+            },
+            Job::Deny => JobSpec {
+                name: "Check dependencies",
+                tool: Some("cargo-deny"),
+                step: Step::new("Run cargo-deny", &["cargo deny check"]),
+                ..job
+            },
+            Job::Audit => JobSpec {
+                name: "Security audit",
+                tool: Some("cargo-audit"),
+                step: Step::new("Run cargo-audit", &["cargo audit"]),
+                scheduled: true,
+                ..job
+            },
+            Job::Miri => JobSpec {
+                name: "Miri",
+                toolchain: Toolchain::Nightly,
+                components: &["miri"],
+                step: Step::new("Run tests under miri", &["cargo miri test"]),
+                ..job
+            },
+            Job::Semver => JobSpec {
+                name: "Semver checks",
+                tool: Some("cargo-semver-checks"),
+                // `cargo semver-checks` fails without a published
+                // baseline, which a new crate doesn't have yet
+                step: Step::new(
+                    "Check for breaking changes",
+                    &[
+                        "if cargo info --registry crates-io {{crate_name}} >/dev/null 2>&1; \
+                       then cargo semver-checks; \
+                       else echo No release of {{crate_name}} on crates.io yet, skipping; fi",
+                    ],
+                ),
+                ..job
+            },
+            Job::Docs => JobSpec {
+                name: "Docs",
+                env: Some(("RUSTDOCFLAGS", "-D warnings")),
+                step: Step::new("Build the docs", &["cargo doc --no-deps"]),
+                ..job
+            },
+        }
+    }
+}
+
+impl fmt::Display for Job {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Job {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Job::ALL
+            .into_iter()
+            .find(|job| job.as_str() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Job::ALL.iter().map(|job| job.as_str()).collect();
+                format!(
+                    "unknown CI job `{}` (expected one of: {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// A [`Job`] as the providers render it.
+struct JobSpec {
+    /// The job's key in the pipeline.
+    id: &'static str,
+    name: &'static str,
+    toolchain: Toolchain,
+    components: &'static [&'static str],
+    /// A cargo subcommand to install first.
+    tool: Option<&'static str>,
+    env: Option<(&'static str, &'static str)>,
+    step: Step,
+    /// A file to keep once the job is done.
+    artifact: Option<&'static str>,
+    /// Runs on a weekly schedule rather than on every push.
+    scheduled: bool,
+}
+
+impl JobSpec {
+    /// A job `id` on `toolchain`, to be filled in.
+    fn new(id: &'static str, toolchain: Toolchain) -> Self {
+        JobSpec {
+            id,
+            name: "",
+            toolchain,
+            components: &[],
+            tool: None,
+            env: None,
+            step: Step::new("", &[]),
+            artifact: None,
+            scheduled: false,
+        }
+    }
+
+    /// Checks that the library would publish, as the release job for
+    /// crates that ship as source.
+    fn publish(toolchain: Toolchain) -> Self {
+        JobSpec {
+            name: "Verify publishing",
+            step: Step::new("Package and verify", &["cargo publish --dry-run"]),
+            ..JobSpec::new("publish", toolchain)
+        }
+    }
+
+    /// Commands preparing a `rust` image for the job, for providers that
+    /// run plain scripts.
+    fn setup(&self) -> Vec<String> {
+        let mut commands = toolchain_setup(self.toolchain, self.components);
+        if let Some(tool) = self.tool {
+            commands.push(format!("cargo install --locked {}", tool));
+        }
+        commands
+    }
+}
+
+/// What the pipeline covers. Every field is optional so the CLI can
+/// override the profile's `[ci]` table one setting at a time; see
+/// [`Matrix::or`].
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Matrix {
+    /// The CI provider, GitHub Actions by default. [`Scaffolder::ci`]
+    /// overrides it.
+    ///
+    /// [`Scaffolder::ci`]: crate::Scaffolder::ci
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<CiProvider>,
+    /// Runners to test on, GitHub Actions only. Defaults to the latest
+    /// Ubuntu, macOS and Windows.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os: Option<Vec<String>>,
+    /// Toolchains to test on, stable by default. The first one also runs
+    /// fmt, clippy and the preset's steps; the others only build and test,
+    /// and nightly is allowed to fail.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toolchains: Option<Vec<Toolchain>>,
+    /// Adds a job checking the crate on this Rust version, which is also
+    /// written to `rust-version` in Cargo.toml.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub msrv: Option<RustVersion>,
+    /// Extra targets to build for, e.g. `aarch64-unknown-linux-gnu`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub targets: Option<Vec<String>>,
+    /// Optional jobs to add.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<Vec<Job>>,
+    /// Release binaries from version tags, or check that a library would
+    /// publish.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release: Option<bool>,
+    /// Targets to build release binaries for; defaults to Linux, macOS
+    /// and Windows on x86_64, plus macOS on ARM.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_targets: Option<Vec<String>>,
+}
+
+impl Matrix {
+    /// Whether every setting is left unset.
+    pub fn is_unset(&self) -> bool {
+        self.provider.is_none()
+            && self.os.is_none()
+            && self.toolchains.is_none()
+            && self.msrv.is_none()
+            && self.targets.is_none()
+            && self.jobs.is_none()
+            && self.release.is_none()
+            && self.release_targets.is_none()
+    }
+
+    /// `self`, with the settings it leaves unset taken from `fallback`.
+    pub fn or(self, fallback: &Matrix) -> Matrix {
+        Matrix {
+            provider: self.provider.or(fallback.provider),
+            os: self.os.or_else(|| fallback.os.clone()),
+            toolchains: self.toolchains.or_else(|| fallback.toolchains.clone()),
+            msrv: self.msrv.or_else(|| fallback.msrv.clone()),
+            targets: self.targets.or_else(|| fallback.targets.clone()),
+            jobs: self.jobs.or_else(|| fallback.jobs.clone()),
+            release: self.release.or(fallback.release),
+            release_targets: self
+                .release_targets
+                .or_else(|| fallback.release_targets.clone()),
+        }
+    }
+
+    pub fn os(&self) -> Vec<&str> {
+        match self.os.as_deref() {
+            Some(os) if !os.is_empty() => os.iter().map(String::as_str).collect(),
+            _ => vec!["ubuntu-latest", "macos-latest", "windows-latest"],
+        }
+    }
+
+    pub fn toolchains(&self) -> Vec<Toolchain> {
+        let mut toolchains: Vec<Toolchain> = Vec::new();
+        for &toolchain in self.toolchains.as_deref().unwrap_or_default() {
+            if !toolchains.contains(&toolchain) {
+                toolchains.push(toolchain);
+            }
+        }
+        if toolchains.is_empty() {
+            toolchains.push(Toolchain::Stable);
+        }
+        toolchains
+    }
+
+    pub fn targets(&self) -> &[String] {
+        self.targets.as_deref().unwrap_or_default()
+    }
+
+    /// The optional jobs, in a fixed order.
+    pub fn jobs(&self) -> Vec<Job> {
+        let requested = self.jobs.as_deref().unwrap_or_default();
+        Job::ALL
+            .into_iter()
+            .filter(|job| requested.contains(job))
+            .collect()
+    }
+
+    pub fn release(&self) -> bool {
+        self.release.unwrap_or(false)
+    }
+
+    pub fn release_targets(&self) -> Vec<&str> {
+        match self.release_targets.as_deref() {
+            Some(targets) if !targets.is_empty() => targets.iter().map(String::as_str).collect(),
+            _ => vec![
+                "x86_64-unknown-linux-gnu",
+                "x86_64-apple-darwin",
+                "aarch64-apple-darwin",
+                "x86_64-pc-windows-msvc",
+            ],
+        }
+    }
+
+    /// The toolchain running every step.
+    fn primary(&self) -> Toolchain {
+        self.toolchains()[0]
+    }
+
+    /// Whether failures on `toolchain` are tolerated.
+    fn may_fail(&self, toolchain: Toolchain) -> bool {
+        toolchain == Toolchain::Nightly && toolchain != self.primary()
+    }
+}
+
+/// A named list of shell commands in the pipeline.
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub name: String,
+    pub commands: Vec<String>,
+    /// Only run once, on Linux, when the pipeline covers several OSes.
+    pub linux_only: bool,
+    /// Run on every toolchain, not just the first.
+    pub all_toolchains: bool,
+}
+
+impl Step {
+    pub fn new(name: impl Into<String>, commands: &[&str]) -> Self {
+        Self {
+            name: name.into(),
+            commands: commands.iter().map(|c| c.to_string()).collect(),
+            linux_only: false,
+            all_toolchains: false,
+        }
+    }
+
+    pub fn linux_only(mut self) -> Self {
+        self.linux_only = true;
+        self
+    }
+
+    pub fn all_toolchains(mut self) -> Self {
+        self.all_toolchains = true;
+        self
+    }
+}
+
+/// The build, test, fmt and clippy steps every pipeline runs. Lints can
+/// change between releases, so only the first toolchain checks them.
+fn standard_steps() -> Vec<Step> {
+    vec![
+        Step::new("Build", &["cargo build --verbose"]).all_toolchains(),
+        Step::new("Run tests", &["cargo test --verbose"]).all_toolchains(),
+        Step::new("Check formatting", &["cargo fmt -- --check"]),
+        Step::new("Run clippy", &["cargo clippy -- -D warnings"]),
+    ]
+}
+
+/// The pipeline files for `provider`: the main one, covering `matrix` and
+/// running the standard steps followed by `extra` (e.g. a preset's), then
+/// whatever the optional jobs and the release need. They are rendered like
+/// any other built-in template, so a user template at the same path
+/// replaces them. `lib` picks the library flavor of the jobs: semver checks,
+/// and a publish check rather than a release workflow.
+pub fn templates(
+    provider: CiProvider,
+    matrix: &Matrix,
+    extra: &[Step],
+    lib: bool,
+    license: &Expression,
+) -> Vec<Template> {
+    let Some(path) = provider.path() else {
+        return Vec::new();
+    };
+    let mut steps = standard_steps();
+    steps.extend(extra.iter().cloned());
+    let mut jobs: Vec<JobSpec> = matrix
+        .jobs()
+        .into_iter()
+        .filter(|&job| lib || job != Job::Semver)
+        .map(|job| job.spec(matrix.primary()))
+        .collect();
+    if matrix.release() && lib {
+        jobs.push(JobSpec::publish(matrix.primary()));
+    }
+
+    let mut templates = Vec::new();
+    let contents = match provider {
+        CiProvider::Github => github(matrix, &steps, &jobs),
+        CiProvider::Gitlab => gitlab(matrix, &steps, &jobs),
+        CiProvider::Forgejo => forgejo(matrix, &steps, &jobs),
+        CiProvider::Woodpecker => woodpecker(matrix, &steps, &jobs),
+        CiProvider::None => unreachable!("`None` has no path"),
+    };
+    templates.push(Template::new(path, contents));
+
+    // Actions run scheduled jobs in a workflow of their own; GitLab and
+    // Woodpecker gate them on scheduled pipelines in the main file
+    if let CiProvider::Github | CiProvider::Forgejo = provider {
+        let dir = path.trim_end_matches("ci.yml");
+        for job in jobs.iter().filter(|job| job.scheduled) {
+            templates.push(Template::new(
+                format!("{}{}.yml", dir, job.id),
+                scheduled_workflow(provider, job),
+            ));
+        }
+    }
+    if matrix.release() && !lib && provider == CiProvider::Github {
+        templates.push(Template::new(
+            ".github/workflows/release.yml",
+            release_workflow(matrix),
+        ));
+    }
+    if matrix.jobs().contains(&Job::Deny) {
+        templates.push(Template::new("deny.toml", deny_config(license)));
+    }
+    templates
+}
+
+/// The GitHub runner that builds for `target` natively.
+fn release_runner(target: &str) -> &'static str {
+    if target.contains("apple") {
+        "macos-latest"
+    } else if target.contains("windows") {
+        "windows-latest"
+    } else if target.starts_with("aarch64-unknown-linux") {
+        "ubuntu-24.04-arm"
+    } else {
+        "ubuntu-latest"
+    }
+}
+
+/// A workflow run on `v*` tags: build the binary for every release target,
+/// package it as `.tar.gz` (`.zip` on Windows) with a SHA-256 checksum, and
+/// publish a GitHub release whose notes are the version's CHANGELOG.md
+/// section.
+fn release_workflow(matrix: &Matrix) -> String {
+    let mut out = String::from(
+        r#"name: Release
+
+on:
+  push:
+    tags: [ "v*" ]
+
+permissions:
+  contents: write
+
+jobs:
+  build:
+    name: Build for ${{ matrix.target }}
+    runs-on: ${{ matrix.os }}
+    strategy:
+      matrix:
+        include:
+"#,
+    );
+    for target in matrix.release_targets() {
+        let _ = write!(
+            out,
+            "          - target: {}\n            os: {}\n",
+            target,
+            release_runner(target)
+        );
+    }
+    out.push_str(
+        r#"
+    steps:
+      - uses: actions/checkout@v4
+
+      - name: Install Rust
+        uses: dtolnay/rust-toolchain@stable
+        with:
+          targets: ${{ matrix.target }}
+
+      - name: Build
+        run: cargo build --release --target ${{ matrix.target }}
+
+      - name: Package
+        shell: bash
+        run: |
+          name="{{crate_name}}-${GITHUB_REF_NAME}-${{ matrix.target }}"
+          mkdir "$name"
+          cp README.md LICENSE* "$name"/
+          if [[ "${{ matrix.target }}" == *windows* ]]; then
+            cp "target/${{ matrix.target }}/release/{{crate_name}}.exe" "$name"/
+            7z a "$name.zip" "$name"
+            sha256sum "$name.zip" > "$name.zip.sha256"
+          else
+            cp "target/${{ matrix.target }}/release/{{crate_name}}" "$name"/
+            tar czf "$name.tar.gz" "$name"
+            shasum -a 256 "$name.tar.gz" > "$name.tar.gz.sha256"
+          fi
+
+      - uses: actions/upload-artifact@v4
+        with:
+          name: ${{ matrix.target }}
+          path: |
+            {{crate_name}}-*.tar.gz
+            {{crate_name}}-*.zip
+            {{crate_name}}-*.sha256
+
+  release:
+    name: Publish the release
+    needs: build
+    runs-on: ubuntu-latest
+
+    steps:
+      - uses: actions/checkout@v4
+
+      - name: Extract the release notes
+        run: |
+          version="${GITHUB_REF_NAME#v}"
+          awk -v version="$version" '
+            /^## / { if (found) exit; found = ($2 == version || $2 == "[" version "]"); next }
+            found { print }
+          ' CHANGELOG.md > notes.md
+
+      - uses: actions/download-artifact@v4
+        with:
+          path: dist
+          merge-multiple: true
+
+      - uses: softprops/action-gh-release@v2
+        with:
+          body_path: notes.md
+          files: dist/*
+"#,
+    );
+    out
+}
+
+/// `deny.toml` for cargo-deny: known advisories and sources only, and the
+/// crate's own licenses.
+fn deny_config(license: &Expression) -> String {
+    let mut ids: Vec<&str> = Vec::new();
+    for license in &license.licenses {
+        if !ids.contains(&license.id) {
+            ids.push(license.id);
+        }
+    }
+    let allow: Vec<String> = ids.iter().map(|id| format!("\"{}\"", id)).collect();
+    format!(
+        r#"# cargo-deny configuration: https://embarkstudios.github.io/cargo-deny/
+
+[advisories]
+ignore = []
+
+[licenses]
+# Add the licenses your dependencies use as you need them
+allow = [{}]
+confidence-threshold = 0.8
+
+[bans]
+multiple-versions = "warn"
+
+[sources]
+unknown-registry = "deny"
+unknown-git = "deny"
+"#,
+        allow.join(", ")
+    )
+}
+
+const ACTIONS_HEADER: &str = r#"name: CI
+
+on:
+  push:
+    branches: [ "main" ]
+  pull_request:
+
+jobs:
+"#;
+
+fn github(matrix: &Matrix, steps: &[Step], jobs: &[JobSpec]) -> String {
+    let os = matrix.os();
+    let toolchains = matrix.toolchains();
+    let primary = matrix.primary();
+    let several = toolchains.len() > 1;
+
+    let mut out = String::from(ACTIONS_HEADER);
+    out.push_str("  test:\n");
+    if several {
+        out.push_str("    name: Test on ${{ matrix.os }} (${{ matrix.toolchain }})\n");
+    } else {
+        out.push_str("    name: Test on ${{ matrix.os }}\n");
+    }
+    out.push_str("    runs-on: ${{ matrix.os }}\n");
+    continue_on_error(&mut out, matrix, &toolchains);
+    out.push_str("    strategy:\n      matrix:\n");
+    let _ = writeln!(out, "        os: [{}]", os.join(", "));
+    if several {
+        let _ = writeln!(out, "        toolchain: [{}]", list(&toolchains));
+    }
+    out.push_str("\n    steps:\n      - uses: actions/checkout@v4\n");
+    out.push_str("\n      - name: Install Rust\n");
+    match (several, primary) {
+        (true, _) => out.push_str(
+            "        uses: dtolnay/rust-toolchain@master\n        with:\n          toolchain: ${{ matrix.toolchain }}\n          components: rustfmt, clippy\n",
+        ),
+        (false, Toolchain::Stable) => out.push_str("        uses: dtolnay/rust-toolchain@stable\n"),
+        (false, toolchain) => {
+            let _ = write!(
+                out,
+                "        uses: dtolnay/rust-toolchain@{}\n        with:\n          components: rustfmt, clippy\n",
+                toolchain
+            );
+        }
+    }
+    for step in steps {
+        let mut conditions = Vec::new();
+        if step.linux_only && os.len() > 1 {
+            conditions.push("runner.os == 'Linux'".to_string());
+        }
+        if !step.all_toolchains && several {
+            conditions.push(format!("matrix.toolchain == '{}'", primary));
+        }
+        actions_step(&mut out, step, &conditions);
+    }
+
+    if let Some(msrv) = &matrix.msrv {
+        let _ = write!(
+            out,
+            r#"
+  msrv:
+    name: Check on Rust {msrv}
+    runs-on: ubuntu-latest
+
+    steps:
+      - uses: actions/checkout@v4
+
+      - name: Install Rust {msrv}
+        uses: dtolnay/rust-toolchain@master
+        with:
+          toolchain: "{msrv}"
+"#
+        );
+        actions_step(&mut out, &msrv_step(), &[]);
+    }
+
+    if !matrix.targets().is_empty() {
+        let _ = write!(
+            out,
+            r#"
+  targets:
+    name: Build for ${{{{ matrix.target }}}}
+    runs-on: ubuntu-latest
+    strategy:
+      matrix:
+        target: [{}]
+
+    steps:
+      - uses: actions/checkout@v4
+
+      - name: Install Rust
+        uses: dtolnay/rust-toolchain@{}
+        with:
+          targets: ${{{{ matrix.target }}}}
+"#,
+            matrix.targets().join(", "),
+            primary
+        );
+        actions_step(
+            &mut out,
+            &Step::new("Build", &["cargo build --target ${{ matrix.target }}"]),
+            &[],
+        );
+    }
+
+    for job in jobs.iter().filter(|job| !job.scheduled) {
+        actions_job(&mut out, CiProvider::Github, job);
+    }
+    out
+}
+
+fn forgejo(matrix: &Matrix, steps: &[Step], jobs: &[JobSpec]) -> String {
+    let toolchains = matrix.toolchains();
+    let primary = matrix.primary();
+    let several = toolchains.len() > 1;
+
+    let mut out = String::from(ACTIONS_HEADER);
+    out.push_str("  test:\n");
+    if several {
+        out.push_str("    name: Test on ${{ matrix.toolchain }}\n");
+    }
+    out.push_str("    runs-on: docker\n");
+    continue_on_error(&mut out, matrix, &toolchains);
+    if several {
+        let _ = write!(
+            out,
+            "    strategy:\n      matrix:\n        toolchain: [{}]\n",
+            list(&toolchains)
+        );
+    }
+    out.push_str("\n    steps:\n      - uses: https://code.forgejo.org/actions/checkout@v4\n");
+    let toolchain = match (several, primary) {
+        (true, _) => Some("${{ matrix.toolchain }}".to_string()),
+        (false, Toolchain::Stable) => None,
+        (false, toolchain) => Some(toolchain.to_string()),
+    };
+    actions_step(
+        &mut out,
+        &rustup_install(toolchain.as_deref(), &["rustfmt", "clippy"]),
+        &[],
+    );
+    for step in steps {
+        let mut conditions = Vec::new();
+        if !step.all_toolchains && several {
+            conditions.push(format!("matrix.toolchain == '{}'", primary));
+        }
+        actions_step(&mut out, step, &conditions);
+    }
+
+    if let Some(msrv) = &matrix.msrv {
+        let _ = write!(
+            out,
+            "\n  msrv:\n    name: Check on Rust {}\n    runs-on: docker\n\n    steps:\n      - uses: https://code.forgejo.org/actions/checkout@v4\n",
+            msrv
+        );
+        actions_step(&mut out, &rustup_install(Some(msrv.as_str()), &[]), &[]);
+        actions_step(&mut out, &msrv_step(), &[]);
+    }
+
+    if !matrix.targets().is_empty() {
+        let _ = write!(
+            out,
+            "\n  targets:\n    name: Build for ${{{{ matrix.target }}}}\n    runs-on: docker\n    strategy:\n      matrix:\n        target: [{}]\n\n    steps:\n      - uses: https://code.forgejo.org/actions/checkout@v4\n",
+            matrix.targets().join(", ")
+        );
+        let toolchain = (primary != Toolchain::Stable).then(|| primary.to_string());
+        actions_step(&mut out, &rustup_install(toolchain.as_deref(), &[]), &[]);
+        actions_step(&mut out, &target_step("${{ matrix.target }}"), &[]);
+    }
+
+    for job in jobs.iter().filter(|job| !job.scheduled) {
+        actions_job(&mut out, CiProvider::Forgejo, job);
+    }
+    out
+}
+
+/// A workflow of its own for a scheduled job, also run when the
+/// dependencies change.
+fn scheduled_workflow(provider: CiProvider, job: &JobSpec) -> String {
+    let mut out = format!(
+        r#"name: {}
+
+on:
+  schedule:
+    - cron: "0 6 * * 1"
+  push:
+    paths:
+      - "**/Cargo.toml"
+      - "**/Cargo.lock"
+
+jobs:
+"#,
+        job.name
+    );
+    let mut body = String::new();
+    actions_job(&mut body, provider, job);
+    out.push_str(body.trim_start_matches('\n'));
+    out
+}
+
+/// An optional job in GitHub or Forgejo Actions syntax.
+fn actions_job(out: &mut String, provider: CiProvider, job: &JobSpec) {
+    let github = provider == CiProvider::Github;
+    let (runner, checkout, upload) = if github {
+        (
+            "ubuntu-latest",
+            "actions/checkout@v4",
+            "actions/upload-artifact@v4",
+        )
+    } else {
+        (
+            "docker",
+            "https://code.forgejo.org/actions/checkout@v4",
+            "https://code.forgejo.org/actions/upload-artifact@v3",
+        )
+    };
+    let _ = write!(
+        out,
+        "\n  {}:\n    name: {}\n    runs-on: {}\n",
+        job.id, job.name, runner
+    );
+    if let Some((key, value)) = job.env {
+        let _ = write!(out, "    env:\n      {}: \"{}\"\n", key, value);
+    }
+    let _ = writeln!(out, "\n    steps:\n      - uses: {}", checkout);
+
+    if github {
+        let _ = writeln!(
+            out,
+            "\n      - name: Install Rust\n        uses: dtolnay/rust-toolchain@{}",
+            job.toolchain
+        );
+        if !job.components.is_empty() {
+            let _ = writeln!(
+                out,
+                "        with:\n          components: {}",
+                job.components.join(", ")
+            );
+        }
+        if let Some(tool) = job.tool {
+            let _ = write!(
+                out,
+                "\n      - name: Install {tool}\n        uses: taiki-e/install-action@v2\n        with:\n          tool: {tool}\n"
+            );
+        }
+    } else {
+        let toolchain = (job.toolchain != Toolchain::Stable).then(|| job.toolchain.to_string());
+        actions_step(
+            out,
+            &rustup_install(toolchain.as_deref(), job.components),
+            &[],
+        );
+        if let Some(tool) = job.tool {
+            let install = format!("cargo install --locked {}", tool);
+            actions_step(
+                out,
+                &Step::new(format!("Install {}", tool), &[&install]),
+                &[],
+            );
+        }
+    }
+    actions_step(out, &job.step, &[]);
+
+    if let Some(artifact) = job.artifact {
+        let _ = write!(
+            out,
+            "\n      - name: Upload the report\n        uses: {}\n        with:\n          name: {}\n          path: {}\n",
+            upload, job.id, artifact
+        );
+    }
+}
+
+/// `continue-on-error` for the toolchains allowed to fail, if any.
+fn continue_on_error(out: &mut String, matrix: &Matrix, toolchains: &[Toolchain]) {
+    if let Some(toolchain) = toolchains.iter().find(|&&t| matrix.may_fail(t)) {
+        let _ = writeln!(
+            out,
+            "    continue-on-error: ${{{{ matrix.toolchain == '{}' }}}}",
+            toolchain
+        );
+    }
+}
+
+/// Install Rust with rustup, for runners that don't come with it.
+fn rustup_install(toolchain: Option<&str>, components: &[&str]) -> Step {
+    let mut command = String::from(
+        "curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh -s -- -y --profile minimal",
+    );
+    if !components.is_empty() {
+        let _ = write!(command, " --component {}", components.join(","));
+    }
+    if let Some(toolchain) = toolchain {
+        let _ = write!(command, " --default-toolchain {}", toolchain);
+    }
+    Step::new(
+        "Install Rust",
+        &[
+            command.as_str(),
+            r#"echo "$HOME/.cargo/bin" >> "$GITHUB_PATH""#,
+        ],
+    )
+}
+
+/// What the MSRV job runs: dev-dependencies often need a newer compiler, so
+/// only the crate itself is checked.
+fn msrv_step() -> Step {
+    Step::new("Check", &["cargo check"])
+}
+
+fn target_step(target: &str) -> Step {
+    Step::new(
+        "Build",
+        &[
+            format!("rustup target add {}", target).as_str(),
+            format!("cargo build --target {}", target).as_str(),
+        ],
+    )
+}
+
+/// A step in GitHub/Forgejo Actions syntax, run only if all `conditions`
+/// hold.
+fn actions_step(out: &mut String, step: &Step, conditions: &[String]) {
+    let _ = write!(out, "\n      - name: {}\n", step.name);
+    if !conditions.is_empty() {
+        let _ = writeln!(out, "        if: {}", conditions.join(" && "));
+    }
+    match step.commands.as_slice() {
+        [command] => {
+            let _ = writeln!(out, "        run: {}", command);
+        }
+        commands => {
+            out.push_str("        run: |\n");
+            for command in commands {
+                let _ = writeln!(out, "          {}", command);
+            }
+        }
+    }
+}
+
+fn gitlab(matrix: &Matrix, steps: &[Step], jobs: &[JobSpec]) -> String {
+    let mut out = String::from(
+        r#"image: rust:latest
+
+workflow:
+  rules:
+    - if: $CI_PIPELINE_SOURCE == "merge_request_event"
+    - if: $CI_COMMIT_BRANCH == "main"
+"#,
+    );
+    if jobs.iter().any(|job| job.scheduled) {
+        out.push_str("    - if: $CI_PIPELINE_SOURCE == \"schedule\"\n");
+    }
+    for (toolchain, steps, primary) in toolchain_jobs(matrix, steps) {
+        let name = if primary {
+            "test".to_string()
+        } else {
+            format!("test:{}", toolchain)
+        };
+        let _ = write!(out, "\n{}:\n", name);
+        before_script(&mut out, &toolchain_setup(toolchain, lints(primary)));
+        out.push_str("  script:\n");
+        script_lines(&mut out, &steps, "    ");
+        if matrix.may_fail(toolchain) {
+            out.push_str("  allow_failure: true\n");
+        }
+    }
+    if let Some(msrv) = &matrix.msrv {
+        let _ = write!(out, "\nmsrv:\n  image: rust:{}\n  script:\n", msrv);
+        script_lines(&mut out, &[msrv_step()], "    ");
+    }
+    for target in matrix.targets() {
+        let _ = write!(out, "\nbuild:{}:\n", target);
+        before_script(&mut out, &toolchain_setup(matrix.primary(), &[]));
+        out.push_str("  script:\n");
+        script_lines(&mut out, &[target_step(target)], "    ");
+    }
+    for job in jobs {
+        let _ = write!(out, "\n{}:\n", job.id);
+        if job.scheduled {
+            out.push_str("  rules:\n    - if: $CI_PIPELINE_SOURCE == \"schedule\"\n");
+        }
+        if let Some((key, value)) = job.env {
+            let _ = write!(out, "  variables:\n    {}: \"{}\"\n", key, value);
+        }
+        before_script(&mut out, &job.setup());
+        out.push_str("  script:\n");
+        script_lines(&mut out, std::slice::from_ref(&job.step), "    ");
+        if let Some(artifact) = job.artifact {
+            let _ = write!(out, "  artifacts:\n    paths:\n      - {}\n", artifact);
+        }
+    }
+    out
+}
+
+/// A GitLab job's `before_script`, if it has any commands.
+fn before_script(out: &mut String, commands: &[String]) {
+    if !commands.is_empty() {
+        out.push_str("  before_script:\n");
+        for command in commands {
+            let _ = writeln!(out, "    - {}", command);
+        }
+    }
+}
+
+fn woodpecker(matrix: &Matrix, steps: &[Step], jobs: &[JobSpec]) -> String {
+    let mut out = String::from(
+        r#"when:
+  - event: push
+    branch: main
+  - event: pull_request
+"#,
+    );
+    if jobs.iter().any(|job| job.scheduled) {
+        out.push_str("  - event: cron\n");
+    }
+    out.push_str("\nsteps:\n");
+    let mut first = true;
+    let mut separate = || if std::mem::take(&mut first) { "" } else { "\n" };
+    for (toolchain, steps, primary) in toolchain_jobs(matrix, steps) {
+        let name = if primary {
+            "test".to_string()
+        } else {
+            format!("test-{}", toolchain)
+        };
+        let _ = write!(out, "{}  {}:\n    image: rust:latest\n", separate(), name);
+        if matrix.may_fail(toolchain) {
+            out.push_str("    failure: ignore\n");
+        }
+        out.push_str("    commands:\n");
+        for command in toolchain_setup(toolchain, lints(primary)) {
+            let _ = writeln!(out, "      - {}", command);
+        }
+        script_lines(&mut out, &steps, "      ");
+    }
+    if let Some(msrv) = &matrix.msrv {
+        let _ = write!(
+            out,
+            "{}  msrv:\n    image: rust:{}\n    commands:\n",
+            separate(),
+            msrv
+        );
+        script_lines(&mut out, &[msrv_step()], "      ");
+    }
+    for target in matrix.targets() {
+        let _ = write!(
+            out,
+            "{}  build-{}:\n    image: rust:latest\n    commands:\n",
+            separate(),
+            target
+        );
+        for command in toolchain_setup(matrix.primary(), &[]) {
+            let _ = writeln!(out, "      - {}", command);
+        }
+        script_lines(&mut out, &[target_step(target)], "      ");
+    }
+    for job in jobs {
+        let _ = write!(out, "{}  {}:\n    image: rust:latest\n", separate(), job.id);
+        if job.scheduled {
+            out.push_str("    when:\n      - event: cron\n");
+        }
+        if let Some((key, value)) = job.env {
+            let _ = write!(out, "    environment:\n      {}: \"{}\"\n", key, value);
+        }
+        out.push_str("    commands:\n");
+        for command in job.setup() {
+            let _ = writeln!(out, "      - {}", command);
+        }
+        script_lines(&mut out, std::slice::from_ref(&job.step), "      ");
+    }
+    out
+}
+
+/// One job per toolchain for providers without a matrix syntax, as
+/// `(toolchain, steps, is_primary)`.
+fn toolchain_jobs(matrix: &Matrix, steps: &[Step]) -> Vec<(Toolchain, Vec<Step>, bool)> {
+    let primary = matrix.primary();
+    matrix
+        .toolchains()
+        .into_iter()
+        .map(|toolchain| {
+            let is_primary = toolchain == primary;
+            let steps = steps
+                .iter()
+                .filter(|step| is_primary || step.all_toolchains)
+                .cloned()
+                .collect();
+            (toolchain, steps, is_primary)
+        })
+        .collect()
+}
+
+/// The components the toolchain running the lints needs.
+fn lints(primary: bool) -> &'static [&'static str] {
+    if primary {
+        &["rustfmt", "clippy"]
+    } else {
+        &[]
+    }
+}
+
+/// Commands switching a `rust` image to `toolchain` with `components`.
+fn toolchain_setup(toolchain: Toolchain, components: &[&str]) -> Vec<String> {
+    match toolchain {
+        Toolchain::Stable if components.is_empty() => Vec::new(),
+        Toolchain::Stable => vec![format!("rustup component add {}", components.join(" "))],
+        toolchain => {
+            let mut install = format!("rustup toolchain install {} --profile minimal", toolchain);
+            if !components.is_empty() {
+                let _ = write!(install, " --component {}", components.join(","));
+            }
+            vec![install, format!("rustup default {}", toolchain)]
+        }
+    }
+}
+
+/// Every step's commands as a YAML list, each step introduced by a comment.
+fn script_lines(out: &mut String, steps: &[Step], indent: &str) {
+    for step in steps {
+        let _ = writeln!(out, "{}# {}", indent, step.name);
+        for command in &step.commands {
+            let _ = writeln!(out, "{}- {}", indent, command);
+        }
+    }
+}
+
+fn list(toolchains: &[Toolchain]) -> String {
+    toolchains
+        .iter()
+        .map(|t| t.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(provider: CiProvider, lib: bool) -> Vec<String> {
+        let matrix = Matrix {
+            jobs: Some(Job::ALL.to_vec()),
+            release: Some(true),
+            ..Matrix::default()
+        };
+        let license = Expression::parse("MIT").unwrap();
+        templates(provider, &matrix, &[], lib, &license)
+            .into_iter()
+            .map(|template| template.path)
+            .collect()
+    }
+
+    #[test]
+    fn generated_paths_per_provider() {
+        assert_eq!(
+            paths(CiProvider::Github, false),
+            [
+                ".github/workflows/ci.yml",
+                ".github/workflows/audit.yml",
+                ".github/workflows/release.yml",
+                "deny.toml",
+            ]
+        );
+        assert_eq!(
+            paths(CiProvider::Github, true),
+            [
+                ".github/workflows/ci.yml",
+                ".github/workflows/audit.yml",
+                "deny.toml",
+            ]
+        );
+        assert_eq!(
+            paths(CiProvider::Forgejo, false),
+            [
+                ".forgejo/workflows/ci.yml",
+                ".forgejo/workflows/audit.yml",
+                "deny.toml",
+            ]
+        );
+        assert_eq!(
+            paths(CiProvider::Gitlab, false),
+            [".gitlab-ci.yml", "deny.toml"]
+        );
+        assert_eq!(
+            paths(CiProvider::Woodpecker, false),
+            [".woodpecker.yml", "deny.toml"]
+        );
+        assert!(paths(CiProvider::None, false).is_empty());
+    }
+
+    #[test]
+    fn semver_checks_wait_for_a_release() {
+        let matrix = Matrix {
+            jobs: Some(vec![Job::Semver]),
+            ..Matrix::default()
+        };
+        let license = Expression::parse("MIT").unwrap();
+        for provider in [
+            CiProvider::Github,
+            CiProvider::Gitlab,
+            CiProvider::Woodpecker,
+        ] {
+            let ci = &templates(provider, &matrix, &[], true, &license)[0].contents;
+            assert!(
+                ci.contains(
+                    "if cargo info --registry crates-io {{crate_name}} >/dev/null 2>&1; \
+                     then cargo semver-checks; \
+                     else echo No release of {{crate_name}} on crates.io yet, skipping; fi\n"
+                ),
+                "{}",
+                ci
+            );
+        }
+    }
+}
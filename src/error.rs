@@ -0,0 +1,199 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+
+use crate::license::LicenseError;
+use crate::template::TemplateError;
+
+/// Everything that can make `cargo setup` fail.
+#[derive(Debug)]
+pub enum Error {
+    /// The profile file exists but isn't valid TOML or has the wrong shape.
+    Profile {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    /// Profile values that don't pass validation, or a key that isn't one.
+    InvalidProfile {
+        path: PathBuf,
+        problems: Vec<String>,
+    },
+    /// No `--config` was given and there is no home directory to find the
+    /// profile file in.
+    NoProfilePath,
+    /// An answers file that can't be parsed or has invalid answers.
+    Answers {
+        path: PathBuf,
+        problems: Vec<String>,
+    },
+    /// A named profile that the profile file doesn't define.
+    UnknownProfile { name: String, path: PathBuf },
+    /// A license that isn't a known SPDX identifier or expression.
+    License {
+        source: LicenseError,
+        /// The file the license came from, when it wasn't a CLI flag.
+        origin: Option<PathBuf>,
+    },
+    /// A broken template, or a template directory that can't be read.
+    Template {
+        path: Option<PathBuf>,
+        source: TemplateError,
+    },
+    /// An MSRV the generated crate can't build with.
+    Msrv { version: String, reason: String },
+    /// The crate name isn't a valid package name.
+    InvalidName { name: String, reason: String },
+    /// An external command (`cargo`, `git`) couldn't be started.
+    Spawn { command: String, source: io::Error },
+    /// An external command ran but exited unsuccessfully.
+    CommandFailed { command: String, status: ExitStatus },
+    /// A Cargo.toml that can't be parsed or lacks what we need.
+    Manifest { path: PathBuf, message: String },
+    /// A `.cargo-setup.lock` that can't be parsed.
+    Lock { path: PathBuf, message: String },
+    /// An update left conflict markers in these files.
+    Conflicts(Vec<String>),
+    /// The crate directory to create is already there.
+    AlreadyExists(PathBuf),
+    /// Reading or writing `path` failed.
+    Io { path: PathBuf, source: io::Error },
+}
+
+impl Error {
+    pub fn io(path: impl AsRef<Path>, source: io::Error) -> Self {
+        Error::Io {
+            path: path.as_ref().to_path_buf(),
+            source,
+        }
+    }
+
+    /// The process exit code for this error. Each kind of failure gets its
+    /// own code so scripts can tell them apart; 2 is left to clap for usage
+    /// errors.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Profile { .. }
+            | Error::InvalidProfile { .. }
+            | Error::NoProfilePath
+            | Error::UnknownProfile { .. } => 3,
+            Error::License { .. } => 4,
+            Error::Template { .. } => 5,
+            Error::Spawn { .. } | Error::CommandFailed { .. } => 6,
+            Error::Manifest { .. } => 7,
+            Error::AlreadyExists(_) => 8,
+            Error::Io { .. } => 9,
+            Error::InvalidName { .. } => 10,
+            Error::Msrv { .. } => 11,
+            Error::Answers { .. } => 12,
+            Error::Lock { .. } => 13,
+            Error::Conflicts(_) => 14,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Profile { path, source } => {
+                write!(f, "invalid profile {}: {}", path.display(), source)
+            }
+            Error::InvalidProfile { path, problems } => {
+                write_problems(f, "invalid profile", path, problems)
+            }
+            Error::Answers { path, problems } => {
+                write_problems(f, "invalid answers file", path, problems)
+            }
+            Error::NoProfilePath => write!(
+                f,
+                "no home directory to keep the profile in; use --config or CARGO_SETUP_CONFIG"
+            ),
+            Error::UnknownProfile { name, path } => {
+                write!(f, "no profile `{}` in {}", name, path.display())
+            }
+            Error::License {
+                source,
+                origin: Some(origin),
+            } => write!(f, "{} in {}", source, origin.display()),
+            Error::License {
+                source,
+                origin: None,
+            } => write!(f, "{}", source),
+            Error::Template {
+                path: Some(path),
+                source,
+            } => write!(f, "invalid template in {}: {}", path.display(), source),
+            Error::Template { path: None, source } => write!(f, "invalid template: {}", source),
+            Error::InvalidName { name, reason } => {
+                write!(f, "invalid package name `{}`: {}", name, reason)
+            }
+            Error::Msrv { version, reason } => {
+                write!(f, "unsupported MSRV `{}`: {}", version, reason)
+            }
+            Error::Spawn { command, source } => {
+                write!(f, "failed to run `{}`: {}", command, source)
+            }
+            Error::CommandFailed { command, status } => {
+                write!(f, "`{}` failed ({})", command, status)
+            }
+            Error::Manifest { path, message } => write!(f, "{}: {}", path.display(), message),
+            Error::Lock { path, message } => {
+                write!(f, "invalid lock file {}: {}", path.display(), message)
+            }
+            Error::Conflicts(paths) => write!(
+                f,
+                "conflicts in {}; resolve the `<<<<<<<` markers",
+                paths.join(", ")
+            ),
+            Error::AlreadyExists(path) => {
+                write!(f, "destination `{}` already exists", path.display())
+            }
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Profile { source, .. } => Some(source),
+            Error::License { source, .. } => Some(source),
+            Error::Template { source, .. } => Some(source),
+            Error::Spawn { source, .. } => Some(source),
+            Error::Io { source, .. } => Some(source),
+            Error::InvalidName { .. }
+            | Error::UnknownProfile { .. }
+            | Error::InvalidProfile { .. }
+            | Error::Answers { .. }
+            | Error::NoProfilePath
+            | Error::Msrv { .. }
+            | Error::CommandFailed { .. }
+            | Error::Manifest { .. }
+            | Error::Lock { .. }
+            | Error::Conflicts(_)
+            | Error::AlreadyExists(_) => None,
+        }
+    }
+}
+
+/// `what` and `path`, then the problems: on the same line if there is one,
+/// else as a list.
+fn write_problems(
+    f: &mut fmt::Formatter<'_>,
+    what: &str,
+    path: &Path,
+    problems: &[String],
+) -> fmt::Result {
+    match problems {
+        [problem] => write!(f, "{} {}: {}", what, path.display(), problem),
+        problems => {
+            write!(f, "{} {}:", what, path.display())?;
+            for problem in problems {
+                write!(f, "\n  - {}", problem)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
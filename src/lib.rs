@@ -0,0 +1,36 @@
+//! Scaffold crates with README, LICENSE, CHANGELOG, tests/, benches/ and CI,
+//! filled in from a [cargo-me](https://crates.io/crates/cargo-me) profile.
+//!
+//! This is the library behind the `cargo setup` subcommand. Start with
+//! [`Scaffolder`].
+
+pub mod answers;
+mod apply;
+pub mod badge;
+pub mod ci;
+pub mod error;
+pub mod license;
+pub mod lock;
+mod manifest;
+mod merge;
+pub mod plan;
+pub mod preset;
+pub mod profile;
+mod scaffold;
+mod skeleton;
+pub mod template;
+mod transaction;
+mod vcs;
+mod workspace;
+
+pub use answers::Answers;
+pub use badge::Badge;
+pub use ci::{CiProvider, Job, Matrix, RustVersion, Toolchain};
+pub use error::{Error, Result};
+pub use lock::Lock;
+pub use plan::Plan;
+pub use preset::Preset;
+pub use profile::{Origin, Profile};
+pub use scaffold::{GeneratedFile, Kind, Member, Report, Scaffolder, Shared};
+pub use skeleton::Edition;
+pub use vcs::Vcs;
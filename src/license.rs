@@ -0,0 +1,526 @@
+use std::fmt;
+
+/// A license whose full text ships inside the binary.
+pub struct License {
+    /// Canonical SPDX identifier.
+    pub id: &'static str,
+    /// Older or deprecated SPDX identifiers that mean the same thing.
+    aliases: &'static [&'static str],
+    /// Suffix used for `LICENSE-<SUFFIX>` when several licenses apply.
+    suffix: &'static str,
+    text: &'static str,
+}
+
+macro_rules! license {
+    ($id:literal, $aliases:expr, $suffix:literal, $file:literal) => {
+        License {
+            id: $id,
+            aliases: $aliases,
+            suffix: $suffix,
+            text: include_str!(concat!("licenses/", $file, ".txt")),
+        }
+    };
+}
+
+pub static LICENSES: &[License] = &[
+    license!("MIT", &[], "MIT", "MIT"),
+    license!("Apache-2.0", &[], "APACHE", "Apache-2.0"),
+    license!("BSD-2-Clause", &[], "BSD-2-CLAUSE", "BSD-2-Clause"),
+    license!("BSD-3-Clause", &[], "BSD-3-CLAUSE", "BSD-3-Clause"),
+    license!("MPL-2.0", &[], "MPL", "MPL-2.0"),
+    license!("GPL-2.0-only", &["GPL-2.0"], "GPL-2.0", "GPL-2.0"),
+    license!("GPL-2.0-or-later", &["GPL-2.0+"], "GPL-2.0", "GPL-2.0"),
+    license!("GPL-3.0-only", &["GPL-3.0"], "GPL-3.0", "GPL-3.0"),
+    license!("GPL-3.0-or-later", &["GPL-3.0+"], "GPL-3.0", "GPL-3.0"),
+    license!("LGPL-2.1-only", &["LGPL-2.1"], "LGPL-2.1", "LGPL-2.1"),
+    license!("LGPL-2.1-or-later", &["LGPL-2.1+"], "LGPL-2.1", "LGPL-2.1"),
+    license!("LGPL-3.0-only", &["LGPL-3.0"], "LGPL-3.0", "LGPL-3.0"),
+    license!("LGPL-3.0-or-later", &["LGPL-3.0+"], "LGPL-3.0", "LGPL-3.0"),
+    license!("AGPL-3.0-only", &["AGPL-3.0"], "AGPL-3.0", "AGPL-3.0"),
+    license!("AGPL-3.0-or-later", &["AGPL-3.0+"], "AGPL-3.0", "AGPL-3.0"),
+    license!("ISC", &[], "ISC", "ISC"),
+    license!("Unlicense", &[], "UNLICENSE", "Unlicense"),
+    license!("0BSD", &[], "0BSD", "0BSD"),
+    license!("Zlib", &[], "ZLIB", "Zlib"),
+];
+
+/// Look up a license by SPDX identifier. Matching is case-insensitive, as
+/// the SPDX spec requires.
+pub fn find(id: &str) -> Option<&'static License> {
+    LICENSES.iter().find(|license| {
+        license.id.eq_ignore_ascii_case(id)
+            || license
+                .aliases
+                .iter()
+                .any(|alias| alias.eq_ignore_ascii_case(id))
+    })
+}
+
+/// A validated SPDX license expression such as `MIT OR Apache-2.0`.
+pub struct Expression {
+    /// The expression rewritten with canonical identifiers, suitable for
+    /// the `license` field of Cargo.toml.
+    pub canonical: String,
+    pub licenses: Vec<&'static License>,
+}
+
+impl Expression {
+    /// Parse `expr` as an SPDX license expression: identifiers joined by
+    /// `OR` and `AND`, grouped with parentheses, optionally followed by
+    /// `WITH <exception>`. The legacy `/` separator that Cargo still
+    /// understands means `OR`.
+    pub fn parse(expr: &str) -> Result<Self, LicenseError> {
+        let tokens = tokenize(expr)?;
+        let mut parser = Parser {
+            expr,
+            tokens: &tokens,
+            next: 0,
+            canonical: Vec::new(),
+            licenses: Vec::new(),
+        };
+        if tokens.is_empty() {
+            return Err(parser.syntax(0, "empty license expression".into()));
+        }
+        parser.or()?;
+        if let Some(&(position, token)) = tokens.get(parser.next) {
+            let message = match token {
+                Token::Close => "`)` without a matching `(`".to_string(),
+                _ => format!("expected `AND`, `OR` or `WITH` before {}", token),
+            };
+            return Err(parser.syntax(position, message));
+        }
+
+        Ok(Self {
+            canonical: parser
+                .canonical
+                .join(" ")
+                .replace("( ", "(")
+                .replace(" )", ")"),
+            licenses: parser.licenses,
+        })
+    }
+
+    /// License files to write, as `(file name, text)` pairs. A single
+    /// license goes into `LICENSE`; several licenses each get their own
+    /// `LICENSE-<SUFFIX>` file, like `LICENSE-MIT` and `LICENSE-APACHE`.
+    ///
+    /// Texts are templates: `{{year}}` and `{{copyright_holder}}` are
+    /// filled in when rendered. Texts without a copyright line (Apache,
+    /// GPL, MPL, ...) stay verbatim.
+    pub fn files(&self) -> Vec<(String, &'static str)> {
+        let mut files: Vec<(String, &'static str)> = Vec::new();
+        for license in &self.licenses {
+            let name = format!("LICENSE-{}", license.suffix);
+            if !files.iter().any(|(existing, _)| *existing == name) {
+                files.push((name, license.text));
+            }
+        }
+        if let [(name, _)] = files.as_mut_slice() {
+            *name = "LICENSE".to_string();
+        }
+        files
+    }
+}
+
+/// SPDX license exceptions accepted after `WITH`.
+static EXCEPTIONS: &[&str] = &[
+    "Autoconf-exception-3.0",
+    "Bison-exception-2.2",
+    "Classpath-exception-2.0",
+    "Font-exception-2.0",
+    "GCC-exception-2.0",
+    "GCC-exception-3.1",
+    "GPL-CC-1.0",
+    "LGPL-3.0-linking-exception",
+    "Linux-syscall-note",
+    "LLVM-exception",
+    "OpenSSL-exception",
+    "Qt-GPL-exception-1.0",
+    "Qt-LGPL-exception-1.1",
+    "Swift-exception",
+    "u-boot-exception-2.0",
+    "WxWindows-exception-3.1",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Token<'a> {
+    Id(&'a str),
+    Or,
+    And,
+    With,
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Id(id) => write!(f, "`{}`", id),
+            Token::Or => f.write_str("`OR`"),
+            Token::And => f.write_str("`AND`"),
+            Token::With => f.write_str("`WITH`"),
+            Token::Open => f.write_str("`(`"),
+            Token::Close => f.write_str("`)`"),
+        }
+    }
+}
+
+/// Split `expr` into tokens and the byte offsets they start at. Operators
+/// are case-insensitive; `/` is `OR`.
+fn tokenize(expr: &str) -> Result<Vec<(usize, Token<'_>)>, LicenseError> {
+    let is_id = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '+');
+    let mut tokens = Vec::new();
+    let mut chars = expr.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            _ if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '/' => Token::Or,
+            _ if is_id(c) => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(i, c)) = chars.peek().filter(|&&(_, c)| is_id(c)) {
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                match &expr[start..end] {
+                    word if word.eq_ignore_ascii_case("or") => Token::Or,
+                    word if word.eq_ignore_ascii_case("and") => Token::And,
+                    word if word.eq_ignore_ascii_case("with") => Token::With,
+                    word => Token::Id(word),
+                }
+            }
+            _ => {
+                return Err(LicenseError::Syntax {
+                    expr: expr.to_string(),
+                    position: start,
+                    message: format!("unexpected `{}`", c),
+                })
+            }
+        };
+        tokens.push((start, token));
+    }
+    Ok(tokens)
+}
+
+/// A recursive-descent parser over the tokens of one expression:
+///
+/// ```text
+/// or     = and { "OR" and }
+/// and    = simple { "AND" simple }
+/// simple = "(" or ")" | license [ "WITH" exception ]
+/// ```
+struct Parser<'a> {
+    expr: &'a str,
+    tokens: &'a [(usize, Token<'a>)],
+    next: usize,
+    canonical: Vec<String>,
+    licenses: Vec<&'static License>,
+}
+
+impl Parser<'_> {
+    fn or(&mut self) -> Result<(), LicenseError> {
+        self.and()?;
+        while self.eat(Token::Or) {
+            self.canonical.push("OR".into());
+            self.and()?;
+        }
+        Ok(())
+    }
+
+    fn and(&mut self) -> Result<(), LicenseError> {
+        self.simple()?;
+        while self.eat(Token::And) {
+            self.canonical.push("AND".into());
+            self.simple()?;
+        }
+        Ok(())
+    }
+
+    fn simple(&mut self) -> Result<(), LicenseError> {
+        let Some(&(position, token)) = self.tokens.get(self.next) else {
+            let message = match self.next.checked_sub(1).map(|i| self.tokens[i].1) {
+                Some(Token::Open) => "expected a license after `(`".to_string(),
+                Some(operator) => format!("expected a license after {}", operator),
+                None => "expected a license".to_string(),
+            };
+            return Err(self.syntax(self.expr.len(), message));
+        };
+        self.next += 1;
+        match token {
+            Token::Open => {
+                self.canonical.push("(".into());
+                self.or()?;
+                if !self.eat(Token::Close) {
+                    return Err(self.syntax(position, "`(` is never closed".into()));
+                }
+                self.canonical.push(")".into());
+            }
+            Token::Id(id) => {
+                let license = find(id).ok_or_else(|| LicenseError::unknown(id, false))?;
+                if !self.licenses.iter().any(|l| l.id == license.id) {
+                    self.licenses.push(license);
+                }
+                self.canonical.push(license.id.to_string());
+                if self.eat(Token::With) {
+                    let exception = match self.tokens.get(self.next) {
+                        Some(&(_, Token::Id(exception))) => exception,
+                        Some(&(position, token)) => {
+                            let message =
+                                format!("expected an exception after `WITH`, found {}", token);
+                            return Err(self.syntax(position, message));
+                        }
+                        None => {
+                            let message = "expected an exception after `WITH`".to_string();
+                            return Err(self.syntax(self.expr.len(), message));
+                        }
+                    };
+                    self.next += 1;
+                    let exception = EXCEPTIONS
+                        .iter()
+                        .find(|e| e.eq_ignore_ascii_case(exception))
+                        .ok_or_else(|| LicenseError::unknown(exception, true))?;
+                    self.canonical.push(format!("WITH {}", exception));
+                }
+            }
+            _ => {
+                let message = format!("expected a license, found {}", token);
+                return Err(self.syntax(position, message));
+            }
+        }
+        Ok(())
+    }
+
+    /// Consume the next token if it is `token`.
+    fn eat(&mut self, token: Token) -> bool {
+        let matches = self.tokens.get(self.next).is_some_and(|&(_, t)| t == token);
+        if matches {
+            self.next += 1;
+        }
+        matches
+    }
+
+    fn syntax(&self, position: usize, message: String) -> LicenseError {
+        LicenseError::Syntax {
+            expr: self.expr.to_string(),
+            position,
+            message,
+        }
+    }
+}
+
+/// Why a license expression was rejected.
+#[derive(Debug)]
+pub enum LicenseError {
+    /// An identifier that isn't one of the bundled SPDX licenses, or an
+    /// exception that isn't a known SPDX exception.
+    Unknown {
+        id: String,
+        exception: bool,
+        suggestion: Option<&'static str>,
+    },
+    /// An expression that doesn't follow the SPDX grammar; `position` is the
+    /// byte offset of the problem in `expr`.
+    Syntax {
+        expr: String,
+        position: usize,
+        message: String,
+    },
+}
+
+impl LicenseError {
+    fn unknown(id: &str, exception: bool) -> Self {
+        // Each candidate with the spellings it is known by
+        let candidates: Vec<(&'static str, Vec<&'static str>)> = if exception {
+            EXCEPTIONS.iter().map(|id| (*id, vec![*id])).collect()
+        } else {
+            LICENSES
+                .iter()
+                .map(|license| {
+                    let mut names = vec![license.id];
+                    names.extend(license.aliases);
+                    (license.id, names)
+                })
+                .collect()
+        };
+        // A transposition such as `MTI` counts as one edit
+        let limit = (id.len() / 2).clamp(1, 3);
+        let id_lower = id.to_ascii_lowercase();
+        let suggestion = candidates
+            .into_iter()
+            .filter_map(|(candidate, names)| {
+                let distance = names
+                    .iter()
+                    .map(|name| strsim::damerau_levenshtein(&name.to_ascii_lowercase(), &id_lower))
+                    .min()?;
+                (distance <= limit).then_some((candidate, distance))
+            })
+            .min_by_key(|(_, distance)| *distance)
+            .map(|(id, _)| id);
+
+        LicenseError::Unknown {
+            id: id.to_string(),
+            exception,
+            suggestion,
+        }
+    }
+}
+
+impl fmt::Display for LicenseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LicenseError::Unknown {
+                id,
+                exception,
+                suggestion,
+            } => {
+                let what = if *exception { "exception" } else { "license" };
+                write!(f, "unknown SPDX {} identifier `{}`", what, id)?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, " (did you mean `{}`?)", suggestion)?;
+                }
+                Ok(())
+            }
+            LicenseError::Syntax {
+                expr,
+                position,
+                message,
+            } => write!(
+                f,
+                "invalid license expression `{}`: {} (at column {})",
+                expr,
+                message,
+                expr[..*position].chars().count() + 1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LicenseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(expr: &str) -> String {
+        match Expression::parse(expr) {
+            Ok(expression) => expression.canonical,
+            Err(err) => panic!("`{}` was rejected: {}", expr, err),
+        }
+    }
+
+    fn syntax_error(expr: &str) -> (usize, String) {
+        match Expression::parse(expr) {
+            Err(LicenseError::Syntax {
+                position, message, ..
+            }) => (position, message),
+            Err(err) => panic!("`{}` failed with a non-syntax error: {}", expr, err),
+            Ok(expression) => panic!("`{}` was accepted as `{}`", expr, expression.canonical),
+        }
+    }
+
+    #[test]
+    fn accepts_valid_expressions() {
+        assert_eq!(canonical("MIT"), "MIT");
+        assert_eq!(canonical("mit or apache-2.0"), "MIT OR Apache-2.0");
+        assert_eq!(canonical("MIT/Apache-2.0"), "MIT OR Apache-2.0");
+        assert_eq!(canonical("GPL-2.0+"), "GPL-2.0-or-later");
+        assert_eq!(
+            canonical("(MIT OR Apache-2.0) AND ISC"),
+            "(MIT OR Apache-2.0) AND ISC"
+        );
+        assert_eq!(canonical("((MIT))"), "((MIT))");
+        assert_eq!(
+            canonical("Apache-2.0 WITH LLVM-exception"),
+            "Apache-2.0 WITH LLVM-exception"
+        );
+        assert_eq!(
+            canonical("MIT OR apache-2.0 with llvm-exception"),
+            "MIT OR Apache-2.0 WITH LLVM-exception"
+        );
+    }
+
+    #[test]
+    fn collects_each_license_once() {
+        let expression = Expression::parse("MIT AND (MIT OR Apache-2.0)").unwrap();
+        let ids: Vec<&str> = expression.licenses.iter().map(|l| l.id).collect();
+        assert_eq!(ids, ["MIT", "Apache-2.0"]);
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        assert_eq!(
+            syntax_error("MIT Apache-2.0"),
+            (
+                4,
+                "expected `AND`, `OR` or `WITH` before `Apache-2.0`".into()
+            )
+        );
+        assert_eq!(
+            syntax_error("MIT OR"),
+            (6, "expected a license after `OR`".into())
+        );
+        assert_eq!(syntax_error("(MIT"), (0, "`(` is never closed".into()));
+        assert_eq!(
+            syntax_error("MIT)"),
+            (3, "`)` without a matching `(`".into())
+        );
+        assert_eq!(
+            syntax_error("MIT AND AND ISC"),
+            (8, "expected a license, found `AND`".into())
+        );
+        assert_eq!(
+            syntax_error("AND MIT"),
+            (0, "expected a license, found `AND`".into())
+        );
+        assert_eq!(
+            syntax_error("MIT/"),
+            (4, "expected a license after `OR`".into())
+        );
+        assert_eq!(
+            syntax_error("MIT WITH"),
+            (8, "expected an exception after `WITH`".into())
+        );
+        assert_eq!(
+            syntax_error("(MIT) WITH LLVM-exception"),
+            (6, "expected `AND`, `OR` or `WITH` before `WITH`".into())
+        );
+        assert_eq!(syntax_error("MIT, ISC"), (3, "unexpected `,`".into()));
+        assert_eq!(syntax_error("  "), (0, "empty license expression".into()));
+    }
+
+    #[test]
+    fn rejects_unknown_identifiers_with_a_suggestion() {
+        let err = Expression::parse("MTI").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "unknown SPDX license identifier `MTI` (did you mean `MIT`?)"
+        );
+        let err = Expression::parse("GLP-3.0").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "unknown SPDX license identifier `GLP-3.0` (did you mean `GPL-3.0-only`?)"
+        );
+        let err = Expression::parse("Apache-2.0 WITH LLVM-exceptoin")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "unknown SPDX exception identifier `LLVM-exceptoin` (did you mean `LLVM-exception`?)"
+        );
+        let err = Expression::parse("Proprietary").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "unknown SPDX license identifier `Proprietary`"
+        );
+    }
+
+    #[test]
+    fn reports_the_column() {
+        let err = Expression::parse("MIT OR OR ISC").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "invalid license expression `MIT OR OR ISC`: expected a license, found `OR` (at column 8)"
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! `.cargo-setup.lock`: what `cargo setup` generated in a crate, so files
+//! that are still untouched scaffolding can be told apart from ones the team
+//! has edited.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::badge::Badge;
+use crate::ci::Matrix;
+use crate::error::{Error, Result};
+use crate::preset::Preset;
+use crate::scaffold::{Kind, Member, Shared};
+use crate::skeleton::Edition;
+use crate::template::{self, Vars};
+use crate::vcs::Vcs;
+
+/// The lock file, at the root of each generated crate and workspace.
+pub const LOCK_FILE: &str = ".cargo-setup.lock";
+
+/// The lock file format this version writes.
+const FORMAT: u32 = 1;
+
+/// Everything a scaffold generated, and what it was generated from.
+///
+/// ```toml
+/// version = 1
+/// generator = "cargo-setup 0.2.1"
+///
+/// [options]
+/// kind = "bin"
+/// license = "MIT"
+/// badges = ["ci", "crates-io"]
+///
+/// [options.ci]
+/// provider = "github"
+/// jobs = ["coverage"]
+///
+/// [vars]
+/// crate_name = "my-tool"
+/// license = "MIT"
+///
+/// [[file]]
+/// path = "README.md"
+/// hash = "sha256:9f86d08..."
+/// contents = """
+/// # my-tool
+/// ..."""
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Lock {
+    /// The lock file format.
+    pub version: u32,
+    /// The `cargo-setup` release whose built-in templates were used.
+    pub generator: String,
+    /// The template directory used over the built-in templates, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template_dir: Option<TemplateDir>,
+    /// The options the files were generated with.
+    #[serde(default)]
+    pub options: Options,
+    /// The variables the templates were rendered with.
+    pub vars: Vars,
+    /// Each generated file, sorted by path.
+    #[serde(default, rename = "file")]
+    pub files: Vec<LockedFile>,
+}
+
+/// The options a scaffold ran with, after the profile filled them in: what
+/// `cargo setup update` needs to generate the same files again.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Options {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Kind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<Preset>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edition: Option<Edition>,
+    /// The canonical SPDX expression.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vcs: Option<Vcs>,
+    /// Shared files generated in a crate inside a workspace anyway.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include: Option<Vec<Shared>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub badges: Option<Vec<Badge>>,
+    /// A workspace's members, as `name` or `name:<kind or preset>`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub members: Option<Vec<Member>>,
+    /// The CI provider and everything the pipeline covers.
+    #[serde(default, skip_serializing_if = "Matrix::is_unset")]
+    pub ci: Matrix,
+}
+
+/// A template directory, and a hash of its files that changes whenever
+/// any of them does.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TemplateDir {
+    pub path: String,
+    pub hash: String,
+}
+
+/// A generated file: what was written to it, and its hash.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LockedFile {
+    /// Relative to the crate, with `/` separators.
+    pub path: String,
+    /// `sha256:` and the hex digest of the contents.
+    pub hash: String,
+    /// The contents as generated, which `cargo setup update` merges edits
+    /// against. Lock files from before it was recorded don't have it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contents: Option<String>,
+}
+
+impl Lock {
+    /// A lock with no files yet, for a scaffold with `options` whose
+    /// templates were rendered with `vars` from the built-in templates and
+    /// `template_dir`.
+    pub fn new(options: Options, vars: Vars, template_dir: Option<&Path>) -> Result<Self> {
+        let template_dir = match template_dir {
+            Some(dir) => {
+                let mut templates = template::load_dir(dir).map_err(|err| Error::io(dir, err))?;
+                templates.sort_by(|a, b| a.path.cmp(&b.path));
+                let mut hasher = Sha256::new();
+                for template in &templates {
+                    // Lengths first, so moving text between files changes the hash
+                    for part in [&template.path, &template.contents] {
+                        hasher.update((part.len() as u64).to_le_bytes());
+                        hasher.update(part.as_bytes());
+                    }
+                }
+                // Absolute, so an update finds it from anywhere
+                let dir = fs::canonicalize(dir).map_err(|err| Error::io(dir, err))?;
+                Some(TemplateDir {
+                    path: dir.display().to_string(),
+                    hash: hex(&hasher.finalize()),
+                })
+            }
+            None => None,
+        };
+        Ok(Lock {
+            version: FORMAT,
+            generator: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            template_dir,
+            options,
+            vars,
+            files: Vec::new(),
+        })
+    }
+
+    /// The lock file in `dir`, or `None` if there is none.
+    pub fn read(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(LOCK_FILE);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::io(&path, err)),
+        };
+        let mut lock: Lock = toml::from_str(&contents).map_err(|err| Error::Lock {
+            path: path.clone(),
+            message: err.to_string(),
+        })?;
+        if lock.version > FORMAT {
+            return Err(Error::Lock {
+                path,
+                message: format!(
+                    "written by a newer cargo-setup (format {}, this one reads up to {})",
+                    lock.version, FORMAT
+                ),
+            });
+        }
+        lock.files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Some(lock))
+    }
+
+    /// Record that `contents` was generated at `path`, replacing any
+    /// earlier entry for it.
+    pub fn record(&mut self, path: &str, contents: &str) {
+        let file = LockedFile {
+            path: path.to_string(),
+            hash: hash(contents.as_bytes()),
+            contents: Some(contents.to_string()),
+        };
+        match self.files.binary_search_by(|f| f.path.as_str().cmp(path)) {
+            Ok(index) => self.files[index] = file,
+            Err(index) => self.files.insert(index, file),
+        }
+    }
+
+    /// The entry for the file generated at `path`, if any.
+    pub fn file(&self, path: &str) -> Option<&LockedFile> {
+        self.files.iter().find(|file| file.path == path)
+    }
+
+    /// Whether `contents` is still what was generated at `path`; `None` if
+    /// the lock doesn't list it.
+    pub fn is_untouched(&self, path: &str, contents: &[u8]) -> Option<bool> {
+        self.file(path).map(|file| file.hash == hash(contents))
+    }
+
+    /// The lock file's contents.
+    pub fn to_toml(&self) -> String {
+        format!(
+            "# Written by cargo-setup: the files it generated, as generated.\n\
+             # Keep it under version control, and don't edit it by hand.\n{}",
+            toml::to_string(self).expect("a lock is always representable as TOML")
+        )
+    }
+}
+
+/// `sha256:` and the hex SHA-256 digest of `contents`.
+pub fn hash(contents: &[u8]) -> String {
+    hex(&Sha256::digest(contents))
+}
+
+fn hex(digest: &[u8]) -> String {
+    let mut out = String::from("sha256:");
+    for byte in digest {
+        write!(out, "{:02x}", byte).expect("writing to a String never fails");
+    }
+    out
+}
@@ -1,45 +1,16 @@
-use clap::Parser;
-use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
-use dirs::home_dir;
-use serde::Deserialize;
-use chrono::Datelike;
-
-const CI_TEMPLATE: &str = r#"
-name: CI
-
-on:
-  push:
-    branches: [ "main" ]
-  pull_request:
-
-jobs:
-  test:
-    name: Test on ${{ matrix.os }}
-    runs-on: ${{ matrix.os }}
-    strategy:
-      matrix:
-        os: [ubuntu-latest, macos-latest, windows-latest]
-
-    steps:
-      - uses: actions/checkout@v4
-
-      - name: Install Rust
-        uses: dtolnay/rust-toolchain@stable
-
-      - name: Build
-        run: cargo build --verbose
-
-      - name: Run tests
-        run: cargo test --verbose
-
-      - name: Check formatting
-        run: cargo fmt -- --check
-
-      - name: Run clippy
-        run: cargo clippy -- -D warnings
-"#;
+use clap::{Args, Parser, Subcommand};
+use std::fmt::Display;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use cargo_setup::answers::ANSWERS_FILE;
+use cargo_setup::template;
+use cargo_setup::{
+    Answers, Badge, CiProvider, Edition, Error, Job, Kind, Matrix, Member, Preset, Profile, Result,
+    RustVersion, Scaffolder, Shared, Toolchain, Vcs,
+};
 
 /// Cargo wrapper so you can run `cargo setup`
 #[derive(Parser)]
@@ -50,179 +21,770 @@ enum Cargo {
 }
 
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct SetupArgs {
-    /// Name of the new crate
-    name: String,
+    #[command(subcommand)]
+    command: Option<SetupCommand>,
+    /// Name of the new crate (or workspace, with --workspace), or a path
+    /// such as crates/foo whose last component is the name
+    #[arg(required_unless_present_any = ["interactive", "answers"])]
+    name: Option<String>,
+    /// Ask for the name, preset, kind, license, CI provider, description and
+    /// keywords, then save the answers in the crate's .cargo-setup-answers.toml
+    #[arg(long, conflicts_with_all = ["workspace", "answers"])]
+    interactive: bool,
+    /// Create the crate from an answers file saved by --interactive; flags
+    /// override its answers
+    #[arg(long, value_name = "FILE", conflicts_with = "workspace")]
+    answers: Option<PathBuf>,
+    /// Create a workspace with the given --member crates instead of a
+    /// single crate
+    #[arg(long, conflicts_with_all = ["bin", "lib", "preset", "use_cargo_new", "include"])]
+    workspace: bool,
+    /// Workspace member to create: NAME, NAME:bin, NAME:lib or NAME:<preset>
+    #[arg(long, value_name = "MEMBER", requires = "workspace")]
+    member: Vec<Member>,
     /// Create a binary (default is library)
-    #[arg(long)]
+    #[arg(long, conflicts_with = "lib")]
     bin: bool,
+    /// Create a library, even if the profile says otherwise
+    #[arg(long)]
+    lib: bool,
+    /// Start from a preset: cli, service, proc-macro, no_std or ffi
+    #[arg(long, value_name = "PRESET", conflicts_with_all = ["bin", "lib"])]
+    preset: Option<Preset>,
+    /// Rust edition: 2015, 2018, 2021 or 2024 (default: 2024)
+    #[arg(long, value_name = "YEAR")]
+    edition: Option<Edition>,
+    /// Version control to initialize: git or none (default: git)
+    #[arg(long, value_name = "VCS")]
+    vcs: Option<Vcs>,
+    /// Create the project with `cargo new` instead of generating it directly
+    #[arg(long, overrides_with = "no_use_cargo_new")]
+    use_cargo_new: bool,
+    /// Generate the project directly, even if the profile says `use-cargo-new`
+    #[arg(long)]
+    no_use_cargo_new: bool,
+    /// Inside a workspace, generate these anyway: ci, license, changelog
+    /// ("" for none, even if the profile lists some)
+    #[arg(long, value_name = "FILES", value_parser = list::<Shared>)]
+    include: Option<List<Shared>>,
+    #[command(flatten)]
+    options: Options,
+}
+
+#[derive(Subcommand)]
+enum SetupCommand {
+    /// Add missing README, LICENSE, CHANGELOG, tests/, benches/, CI and
+    /// metadata to an existing crate
+    Apply(ApplyArgs),
+    /// Regenerate the scaffolded files of a crate or workspace root from the
+    /// current templates, with the options it was created with, merging in
+    /// the edits made since
+    Update(ApplyArgs),
+    /// Show, create, edit or check the profile file
+    Profile(ProfileArgs),
+    /// Print the settings for a crate at a path: the profile with the
+    /// `.cargo-setup.toml` files above it layered on top
+    Config(ConfigArgs),
+}
+
+#[derive(Args)]
+struct ConfigArgs {
+    /// Where the crate is or would be created
+    #[arg(default_value = ".")]
+    path: PathBuf,
+    /// Show the file, environment variable or git key each setting came from
+    #[arg(long)]
+    show_origin: bool,
+    /// Profile file to use instead of the default one
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Named profile to use instead of the one picked by path
+    #[arg(long, value_name = "NAME", env = "CARGO_SETUP_PROFILE")]
+    profile: Option<String>,
+}
+
+#[derive(Args)]
+struct ProfileArgs {
+    #[command(subcommand)]
+    command: ProfileCommand,
+    /// Profile file to use instead of the default one
+    #[arg(long, value_name = "PATH", global = true)]
+    config: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum ProfileCommand {
+    /// Print the profile that applies to the current directory
+    Show {
+        /// Named profile to show instead of the one picked by path
+        #[arg(long, value_name = "NAME", env = "CARGO_SETUP_PROFILE")]
+        profile: Option<String>,
+    },
+    /// Create the profile file, asking for anything not given on a terminal
+    Init {
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        email: Option<String>,
+        /// GitHub username
+        #[arg(long)]
+        github: Option<String>,
+        /// Default license, as an SPDX expression
+        #[arg(long)]
+        license: Option<String>,
+        #[arg(long)]
+        organization: Option<String>,
+        /// Replace an existing profile file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Set a key, e.g. `email` or `ci.toolchains` (lists are comma-separated)
+    Set {
+        key: String,
+        value: String,
+        /// Edit this named profile instead of the default one
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+    },
+    /// Remove a key
+    Unset {
+        key: String,
+        /// Edit this named profile instead of the default one
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+    },
+    /// Check emails, GitHub handles, licenses and URLs in every profile
+    Validate,
+    /// Print where the profile file is
+    Path,
+}
+
+#[derive(Args)]
+struct ApplyArgs {
+    /// Path to the existing crate
+    #[arg(default_value = ".")]
+    path: PathBuf,
+    #[command(flatten)]
+    options: Options,
+}
+
+/// Options shared by `cargo setup <name>` and `cargo setup apply`.
+#[derive(Args)]
+struct Options {
+    /// Profile file to use instead of the default one
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Named profile to use instead of the one picked by path
+    #[arg(long, value_name = "NAME", env = "CARGO_SETUP_PROFILE")]
+    profile: Option<String>,
     /// License override (e.g. MIT, Apache-2.0)
     #[arg(long)]
     license: Option<String>,
+    /// CI pipeline to generate: github, gitlab, forgejo, woodpecker or none
+    /// (default: github)
+    #[arg(long, value_name = "PROVIDER")]
+    ci: Option<CiProvider>,
+    /// Runners to test on, GitHub Actions only (default: ubuntu-latest,
+    /// macos-latest, windows-latest)
+    #[arg(long, value_name = "OS", value_parser = list::<String>)]
+    ci_os: Option<List<String>>,
+    /// Toolchains to test on: stable, beta, nightly (default: stable)
+    #[arg(long, value_name = "TOOLCHAINS", value_parser = list::<Toolchain>)]
+    ci_toolchains: Option<List<Toolchain>>,
+    /// Minimum supported Rust version: written to `rust-version` and
+    /// checked by its own CI job
+    #[arg(long, value_name = "VERSION")]
+    msrv: Option<RustVersion>,
+    /// Extra targets for CI to build for (e.g. aarch64-unknown-linux-gnu)
+    #[arg(long, value_name = "TARGETS", value_parser = list::<String>)]
+    ci_targets: Option<List<String>>,
+    /// Extra CI jobs: coverage, deny, audit, miri, semver, docs ("" for none)
+    #[arg(long, value_name = "JOBS", value_parser = list::<Job>)]
+    ci_jobs: Option<List<Job>>,
+    /// Add a release workflow for version tags (binaries), or a check that
+    /// the crate would publish (libraries)
+    #[arg(long, overrides_with = "no_release")]
+    release: bool,
+    /// No release workflow or publish check, even if the profile asks for one
+    #[arg(long)]
+    no_release: bool,
+    /// Targets to build release binaries for (default: x86_64 Linux, macOS
+    /// and Windows, and aarch64 macOS)
+    #[arg(long, value_name = "TARGETS", value_parser = list::<String>)]
+    release_targets: Option<List<String>>,
+    /// `description` for `[package]`, also shown in the README
+    #[arg(long)]
+    description: Option<String>,
+    /// crates.io keywords for `[package]`, at most 5 ("" for none)
+    #[arg(long, value_name = "KEYWORDS", value_parser = keywords)]
+    keywords: Option<List<String>>,
+    /// README badges: ci, crates-io, docs-rs, license (default: ci; "" for
+    /// none)
+    #[arg(long, value_name = "BADGES", value_parser = list::<Badge>)]
+    badges: Option<List<Badge>>,
+    /// Template directory whose files override the built-in ones
+    /// (default: $XDG_CONFIG_HOME/cargo-setup/templates)
+    #[arg(long, value_name = "DIR")]
+    template: Option<PathBuf>,
+    /// Print the files that would be generated without writing anything
+    #[arg(long)]
+    dry_run: bool,
+    /// Print each profile value and where it came from, then stop
+    #[arg(long)]
+    explain_profile: bool,
 }
 
-#[derive(Deserialize)]
-struct Profile {
-    name: Option<String>,
-    email: Option<String>,
-    github: Option<String>,
-    license: Option<String>,
-    organization: Option<String>,
+/// A comma-separated list option. Not spelled `Vec`, so clap hands the whole
+/// value to [`list`] and an empty value is an empty list, which overrides
+/// the profile's rather than falling back to it.
+type List<T> = Vec<T>;
+
+/// Parse a comma-separated list, ignoring blanks around and between items.
+fn list<T: FromStr>(value: &str) -> std::result::Result<List<T>, String>
+where
+    T::Err: Display,
+{
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| item.parse().map_err(|err: T::Err| err.to_string()))
+        .collect()
 }
 
-impl Profile {
-    fn path() -> PathBuf {
-        home_dir().unwrap().join(".cargo-me.toml")
-    }
+/// `--keywords`: checked like the profile's `keywords`.
+fn keywords(value: &str) -> std::result::Result<List<String>, String> {
+    Answers::check("keywords", value)?;
+    list(value)
+}
 
-    fn load() -> Option<Self> {
-        let path = Self::path();
-        if path.exists() {
-            let contents = fs::read_to_string(&path).ok()?;
-            toml::from_str(&contents).ok()
-        } else {
-            None
+fn main() -> ExitCode {
+    let result = match Cargo::parse() {
+        Cargo::Setup(args) => match args.command {
+            Some(SetupCommand::Apply(apply_args)) => apply(apply_args),
+            Some(SetupCommand::Update(update_args)) => update(update_args),
+            Some(SetupCommand::Profile(profile_args)) => profile(profile_args),
+            Some(SetupCommand::Config(config_args)) => config(config_args),
+            None => setup(args),
+        },
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::from(err.exit_code())
         }
     }
 }
 
-fn main() {
-    match Cargo::parse() {
-        Cargo::Setup(args) => {
-            let profile = Profile::load();
-            let license = args
-                .license
-                .or_else(|| profile.as_ref().and_then(|p| p.license.clone()))
-                .unwrap_or_else(|| "MIT".to_string());
+/// `cargo setup <name>`: scaffold a brand-new crate.
+fn setup(mut args: SetupArgs) -> Result<()> {
+    let answers = if args.interactive {
+        Some(wizard(&args)?)
+    } else if let Some(path) = &args.answers {
+        Some(Answers::load(path)?)
+    } else {
+        None
+    };
+    if let Some(answers) = &answers {
+        args.fill(answers.clone());
+    }
+    let Some(name) = args.name.clone() else {
+        return Err(Error::Answers {
+            path: args.answers.clone().unwrap_or_default(),
+            problems: vec!["no `name`; give one on the command line".into()],
+        });
+    };
+    if args.workspace {
+        return setup_workspace(name, args);
+    }
+    let target = PathBuf::from(&name);
+    if args.options.explain_profile {
+        return args.options.explain(&target);
+    }
+    let mut scaffolder = Scaffolder::new(package_name(&target)?).path(&target);
+    if args.bin {
+        scaffolder = scaffolder.kind(Kind::Bin);
+    } else if args.lib {
+        scaffolder = scaffolder.kind(Kind::Lib);
+    }
+    if let Some(preset) = args.preset {
+        scaffolder = scaffolder.preset(preset);
+    }
+    if let Some(edition) = args.edition {
+        scaffolder = scaffolder.edition(edition);
+    }
+    if let Some(vcs) = args.vcs {
+        scaffolder = scaffolder.vcs(vcs);
+    }
+    if args.use_cargo_new || args.no_use_cargo_new {
+        scaffolder = scaffolder.use_cargo_new(args.use_cargo_new);
+    }
+    if let Some(include) = args.include {
+        scaffolder = scaffolder.include_only(include);
+    }
+    let scaffolder = args.options.configure(scaffolder, &target, true)?;
+
+    if args.options.dry_run {
+        print!("{}", scaffolder.plan()?);
+        return Ok(());
+    }
+
+    let report = scaffolder.generate()?;
+    if let Some(workspace) = &report.workspace {
+        println!("  member of the workspace at {}", workspace.display());
+    }
+    if let Some(answers) = answers.filter(|_| args.interactive) {
+        answers.write(&report.path.join(ANSWERS_FILE))?;
+        println!("  saved    {} (replay with --answers)", ANSWERS_FILE);
+    }
+    for reason in &report.skipped {
+        println!("  skipped  {}", reason);
+    }
+    println!(
+        "✅ Scaffolded project `{}` with license `{}` and extras.",
+        report.name, report.license
+    );
+    Ok(())
+}
+
+/// The package name for a `<name>` argument, which may be a path such as
+/// `crates/foo`: its last component, as with `cargo new`.
+fn package_name(target: &Path) -> Result<String> {
+    match target.file_name().and_then(|name| name.to_str()) {
+        Some(name) => Ok(name.to_string()),
+        None => Err(Error::InvalidName {
+            name: target.display().to_string(),
+            reason: "the path doesn't end in a directory name".into(),
+        }),
+    }
+}
+
+/// `cargo setup --workspace <name>`: scaffold a workspace and its members.
+fn setup_workspace(name: String, args: SetupArgs) -> Result<()> {
+    let target = PathBuf::from(&name);
+    if args.options.explain_profile {
+        return args.options.explain(&target);
+    }
+    let mut scaffolder = Scaffolder::workspace(package_name(&target)?).path(&target);
+    if let Some(edition) = args.edition {
+        scaffolder = scaffolder.edition(edition);
+    }
+    if let Some(vcs) = args.vcs {
+        scaffolder = scaffolder.vcs(vcs);
+    }
+    for member in args.member {
+        scaffolder = scaffolder.member(member);
+    }
+    let scaffolder = args.options.configure(scaffolder, &target, true)?;
+
+    if args.options.dry_run {
+        print!("{}", scaffolder.plan()?);
+        return Ok(());
+    }
+
+    let report = scaffolder.generate()?;
+    for reason in &report.skipped {
+        println!("  skipped  {}", reason);
+    }
+    println!(
+        "✅ Scaffolded workspace `{}` with license `{}` and extras.",
+        report.name, report.license
+    );
+    Ok(())
+}
+
+/// `cargo setup apply [path]`: add whatever an existing crate is missing,
+/// never replacing a file that is already there.
+fn apply(args: ApplyArgs) -> Result<()> {
+    if args.options.explain_profile {
+        return args.options.explain(&args.path);
+    }
+    let scaffolder = args
+        .options
+        .configure(Scaffolder::retrofit(&args.path), &args.path, true)?;
+
+    if args.options.dry_run {
+        print!("{}", scaffolder.plan()?);
+        return Ok(());
+    }
+
+    let report = scaffolder.generate()?;
+    for file in &report.files {
+        let verb = if file.updated { "updated" } else { "added" };
+        println!("  {:<8} {}", verb, file.path);
+    }
+    for reason in &report.skipped {
+        println!("  skipped  {}", reason);
+    }
+    println!(
+        "✅ Applied scaffolding to `{}`: {} written, {} skipped.",
+        report.name,
+        report.files.len(),
+        report.skipped.len()
+    );
+    Ok(())
+}
+
+/// `cargo setup update [path]`: bring the files listed in the crate's
+/// `.cargo-setup.lock` up to date with the templates.
+fn update(args: ApplyArgs) -> Result<()> {
+    if args.options.explain_profile {
+        return args.options.explain(&args.path);
+    }
+    // The template directory is the one the lock file recorded, unless
+    // given with --template
+    let scaffolder = args
+        .options
+        .configure(Scaffolder::update(&args.path), &args.path, false)?;
+
+    if args.options.dry_run {
+        print!("{}", scaffolder.plan()?);
+        return Ok(());
+    }
+
+    let report = scaffolder.generate()?;
+    for file in &report.files {
+        let verb = if report.conflicts.contains(&file.path) {
+            "conflict"
+        } else if file.updated {
+            "updated"
+        } else {
+            "added"
+        };
+        println!("  {:<8} {}", verb, file.path);
+    }
+    for reason in &report.skipped {
+        println!("  skipped  {}", reason);
+    }
+    if !report.conflicts.is_empty() {
+        return Err(Error::Conflicts(report.conflicts));
+    }
+    println!(
+        "✅ Updated `{}`: {} written, {} skipped.",
+        report.name,
+        report.files.len(),
+        report.skipped.len()
+    );
+    Ok(())
+}
 
-            // 1. Run cargo new
-            let mut cmd = Command::new("cargo");
-            cmd.arg("new").arg(&args.name);
-            if args.bin {
-                cmd.arg("--bin");
+/// `cargo setup profile ...`: manage the profile file.
+fn profile(args: ProfileArgs) -> Result<()> {
+    let config = args.config.or_else(Profile::path);
+    // Reading without a profile file location finds no profile; writing needs one
+    let path = || config.as_deref().ok_or(Error::NoProfilePath);
+    match args.command {
+        ProfileCommand::Show { profile } => {
+            let Some(resolved) =
+                Profile::select(config.as_deref(), profile.as_deref(), Path::new("."))?
+            else {
+                println!("# no profile file");
+                return Ok(());
+            };
+            let source = path()?.display();
+            match &resolved.selected {
+                Some(name) => println!("# profile `{}` from {}", name, source),
+                None => println!("# from {}", source),
             }
-            let status = cmd.status().expect("failed to run cargo new");
-            if !status.success() {
-                eprintln!("cargo new failed");
-                return;
+            print!("{}", resolved.to_toml());
+        }
+        ProfileCommand::Init {
+            name,
+            email,
+            github,
+            license,
+            organization,
+            force,
+        } => {
+            let fields = [
+                ("name", name, "Name"),
+                ("email", email, "Email"),
+                ("github", github, "GitHub username"),
+                (
+                    "license",
+                    license,
+                    "Default license (e.g. MIT OR Apache-2.0)",
+                ),
+                ("organization", organization, "Organization"),
+            ];
+            let interactive = io::stdin().is_terminal();
+            let mut values = Vec::new();
+            for (key, value, question) in fields {
+                let value = match value {
+                    Some(value) => value,
+                    None if interactive => ask(question)?,
+                    None => String::new(),
+                };
+                values.push((key, value));
             }
-
-            let crate_path = PathBuf::from(&args.name);
-
-            // 2. Enhance Cargo.toml
-            let cargo_toml_path = crate_path.join("Cargo.toml");
-            let mut cargo_toml = fs::read_to_string(&cargo_toml_path).unwrap();
-
-            if let Some(profile) = &profile {
-                if let Some(author) = &profile.name {
-                    let email = profile.email.clone().unwrap_or_default();
-                    cargo_toml.push_str(&format!("authors = [\"{} <{}>\"]\n", author, email));
-                }
-                cargo_toml.push_str(&format!("license = \"{}\"\n", license));
-                if let Some(gh) = &profile.github {
-                    cargo_toml.push_str(&format!(
-                        "repository = \"https://github.com/{}/{}\"\n",
-                        gh, args.name
-                    ));
-                }
+            Profile::init(path()?, &values, force)?;
+            println!("✅ Wrote {}", path()?.display());
+        }
+        ProfileCommand::Set {
+            key,
+            value,
+            profile,
+        } => {
+            Profile::set(path()?, &key, &value, profile.as_deref())?;
+            println!("✅ Set `{}` in {}", key, path()?.display());
+        }
+        ProfileCommand::Unset { key, profile } => {
+            if Profile::unset(path()?, &key, profile.as_deref())? {
+                println!("✅ Removed `{}` from {}", key, path()?.display());
+            } else {
+                println!("`{}` is not set", key);
             }
-            fs::write(&cargo_toml_path, cargo_toml).unwrap();
-
-            // 3. Add README.md with CI badge + install instructions
-            let readme_path = crate_path.join("README.md");
-            if !readme_path.exists() {
-                let gh_user_owned = profile
-                    .as_ref()
-                    .and_then(|p| p.github.clone())
-                    .unwrap_or_else(|| "your-github".to_string());
-                let gh_user = &gh_user_owned;
-
-                let ci_badge = format!(
-                    "[![CI](https://github.com/{}/{}/actions/workflows/ci.yml/badge.svg)](https://github.com/{}/{}/actions)",
-                    gh_user, args.name, gh_user, args.name
-                );
-
-                let mut readme = format!("# {}\n\n{}\n\n", args.name, ci_badge);
-
-                if let Some(profile) = &profile {
-                    if let Some(gh) = &profile.github {
-                        readme.push_str(&format!(
-                            "Created by [{}](https://github.com/{})\n\n",
-                            gh, gh
-                        ));
-                    }
-                }
-
-                readme.push_str(&format!(
-                    "## 📦 Installation\n\n```bash\ncargo install {}\n```\n\n",
-                    args.name
-                ));
-
-                readme.push_str(
-                    "## 🚀 Usage\n\n```rust\nfn main() {\n    println!(\"Hello from your new crate!\");\n}\n```\n",
-                );
-
-                fs::write(readme_path, readme).unwrap();
+        }
+        ProfileCommand::Validate => {
+            if Profile::validate(path()?)? {
+                println!("✅ {} is valid", path()?.display());
+            } else {
+                println!("no profile at {}", path()?.display());
             }
+        }
+        ProfileCommand::Path => println!("{}", path()?.display()),
+    }
+    Ok(())
+}
 
-            // 4. Add LICENSE
-            let license_path = crate_path.join("LICENSE");
-            if !license_path.exists() {
-                let year = chrono::Utc::now().year();
-                let org = profile
-                    .as_ref()
-                    .and_then(|p| p.organization.clone())
-                    .unwrap_or_else(|| "Your Org".into());
-
-                let license_text = format!(
-                    "Copyright (c) {} {}\n\nLicensed under the {} license.",
-                    year, org, license
-                );
-                fs::write(license_path, license_text).unwrap();
-            }
+/// `cargo setup config [path]`: every setting for a crate at `path`, as
+/// `key = value` lines like `cargo config get`.
+fn config(args: ConfigArgs) -> Result<()> {
+    let config = args.config.or_else(Profile::path);
+    let profile = Profile::resolve(config.as_deref(), args.profile.as_deref(), &args.path)?
+        .unwrap_or_default();
+    for (key, value) in profile.values() {
+        match profile.origins.get(&key).filter(|_| args.show_origin) {
+            Some(origin) => println!("{} = {} # {}", key, value, origin),
+            None => println!("{} = {}", key, value),
+        }
+    }
+    Ok(())
+}
 
-            // 5. Add tests/ and benches/
-            let test_path = crate_path.join("tests");
-            fs::create_dir_all(&test_path).unwrap();
-            fs::write(
-                test_path.join("basic.rs"),
-                "#[test]\nfn it_works() {\n    assert_eq!(2+2, 4);\n}\n",
-            )
-            .unwrap();
-
-            let bench_path = crate_path.join("benches");
-            fs::create_dir_all(&bench_path).unwrap();
-            fs::write(
-                bench_path.join("bench.rs"),
-                "// Basic benchmark (requires criterion)\nfn main() { println!(\"Run with cargo bench\"); }\n",
-            )
-            .unwrap();
-
-            // 6. Add CHANGELOG.md
-            let changelog_path = crate_path.join("CHANGELOG.md");
-            if !changelog_path.exists() {
-                let changelog = format!(
-                    "# Changelog\n\nAll notable changes to `{}` will be documented here.\n\n\
-                    The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.0.0/),\n\
-                    and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).\n\n\
-                    ## [Unreleased]\n- Initial scaffold\n",
-                    args.name
-                );
-                fs::write(changelog_path, changelog).unwrap();
-            }
+/// Prompt for a value on the terminal; an empty answer leaves it unset.
+fn ask(question: &str) -> Result<String> {
+    print!("{}: ", question);
+    Ok(read_answer()?.unwrap_or_default())
+}
+
+/// A line from stdin, trimmed, or `None` at the end of input.
+fn read_answer() -> Result<Option<String>> {
+    io::stdout()
+        .flush()
+        .map_err(|err| Error::io("stdout", err))?;
+    let mut answer = String::new();
+    let read = io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(|err| Error::io("stdin", err))?;
+    Ok((read > 0).then(|| answer.trim().to_string()))
+}
 
-            // 7. Add GitHub Actions CI workflow
-            let ci_path = crate_path.join(".github/workflows");
-            fs::create_dir_all(&ci_path).unwrap();
-            let ci_file = ci_path.join("ci.yml");
-            if !ci_file.exists() {
-                fs::write(&ci_file, CI_TEMPLATE).unwrap();
+/// `--interactive`: ask for each answer, offering the flags given and then
+/// the profile's values as defaults.
+fn wizard(args: &SetupArgs) -> Result<Answers> {
+    let name = prompt("Crate name", "name", args.name.clone())?
+        .expect("a name is required to pass the check");
+    let options = &args.options;
+    let profile = Profile::resolve(
+        options.config_path().as_deref(),
+        options.profile.as_deref(),
+        Path::new(&name),
+    )?
+    .unwrap_or_default();
+
+    // A preset decides the kind, so the kind is only asked for without one,
+    // and `--bin` or `--lib` rule out a preset
+    let flag_kind = match (args.bin, args.lib) {
+        (true, _) => Some(Kind::Bin),
+        (_, true) => Some(Kind::Lib),
+        _ => None,
+    };
+    let preset = match flag_kind {
+        Some(_) => None,
+        None => {
+            let preset = args.preset.or(profile.preset).map(|p| p.to_string());
+            prompt(
+                "Preset (none, cli, service, proc-macro, no-std, ffi)",
+                "preset",
+                Some(preset.unwrap_or_else(|| "none".into())),
+            )?
+            .and_then(|preset| preset.parse::<Preset>().ok())
+        }
+    };
+    let kind = match (preset, flag_kind) {
+        (Some(_), _) => None,
+        (None, Some(kind)) => Some(kind.as_str().into()),
+        (None, None) => prompt(
+            "Kind (bin, lib)",
+            "kind",
+            Some(profile.kind.unwrap_or_default().as_str().into()),
+        )?,
+    };
+    let license = options.license.clone().or(profile.license.clone());
+    let license = prompt(
+        "License (SPDX expression)",
+        "license",
+        Some(license.unwrap_or_else(|| "MIT".into())),
+    )?;
+    let ci = options.ci.or(profile.ci.provider).unwrap_or_default();
+    let ci = prompt(
+        "CI provider (github, gitlab, forgejo, woodpecker, none)",
+        "ci",
+        Some(ci.as_str().into()),
+    )?;
+    let description = prompt("Description", "description", options.description.clone())?;
+    let keywords = options.keywords.clone().or(profile.keywords.clone());
+    let keywords = prompt(
+        "Keywords (comma-separated)",
+        "keywords",
+        keywords.map(|keywords| keywords.join(", ")),
+    )?;
+
+    Ok(Answers {
+        name: Some(name),
+        kind: kind.and_then(|kind| kind.parse().ok()),
+        license,
+        preset,
+        ci: ci.and_then(|ci| ci.parse().ok()),
+        description,
+        keywords: keywords.map(|keywords| {
+            keywords
+                .split(',')
+                .map(str::trim)
+                .filter(|keyword| !keyword.is_empty())
+                .map(str::to_string)
+                .collect()
+        }),
+    })
+}
+
+/// Ask `question` until the answer passes [`Answers::check`] for `key`. An
+/// empty answer takes `default`; `None` when there is neither.
+fn prompt(question: &str, key: &str, default: Option<String>) -> Result<Option<String>> {
+    loop {
+        match &default {
+            Some(default) => print!("{} [{}]: ", question, default),
+            None => print!("{}: ", question),
+        }
+        let answer = match read_answer()? {
+            Some(answer) if !answer.is_empty() => Some(answer),
+            Some(_) => default.clone(),
+            None => {
+                return Err(Error::io(
+                    "stdin",
+                    io::Error::new(io::ErrorKind::UnexpectedEof, "no answer"),
+                ))
             }
+        };
+        match Answers::check(key, answer.as_deref().unwrap_or_default()) {
+            Ok(()) => return Ok(answer),
+            Err(problem) => println!("  {}", problem),
+        }
+    }
+}
+
+impl SetupArgs {
+    /// Fill in what the flags leave unset from `answers`.
+    fn fill(&mut self, answers: Answers) {
+        self.name = self.name.take().or(answers.name);
+        if !self.bin && !self.lib && self.preset.is_none() {
+            self.bin = answers.kind == Some(Kind::Bin);
+            self.lib = answers.kind == Some(Kind::Lib);
+            self.preset = answers.preset;
+        }
+        let options = &mut self.options;
+        options.license = options.license.take().or(answers.license);
+        options.ci = options.ci.or(answers.ci);
+        options.description = options.description.take().or(answers.description);
+        options.keywords = options.keywords.take().or(answers.keywords);
+    }
+}
 
-            println!(
-                "✅ Scaffolded project `{}` with license `{}` and extras.",
-                args.name, license
-            );
+impl Options {
+    /// Apply the shared options, the profile for a crate at `target` and,
+    /// with `default_templates`, the default template directory to
+    /// `scaffolder`.
+    fn configure(
+        &self,
+        mut scaffolder: Scaffolder,
+        target: &Path,
+        default_templates: bool,
+    ) -> Result<Scaffolder> {
+        let profile = Profile::resolve(
+            self.config_path().as_deref(),
+            self.profile.as_deref(),
+            target,
+        )?;
+        let profile_template = profile.as_ref().is_some_and(|p| p.template.is_some());
+        if let Some(profile) = profile {
+            scaffolder = scaffolder.profile(profile);
+        }
+        if let Some(license) = &self.license {
+            scaffolder = scaffolder.license(license);
+        }
+        if let Some(ci) = self.ci {
+            scaffolder = scaffolder.ci(ci);
+        }
+        scaffolder = scaffolder.ci_matrix(Matrix {
+            provider: None,
+            os: self.ci_os.clone(),
+            toolchains: self.ci_toolchains.clone(),
+            msrv: self.msrv.clone(),
+            targets: self.ci_targets.clone(),
+            jobs: self.ci_jobs.clone(),
+            release: (self.release || self.no_release).then_some(self.release),
+            release_targets: self.release_targets.clone(),
+        });
+        if let Some(description) = &self.description {
+            scaffolder = scaffolder.description(description);
+        }
+        if let Some(keywords) = &self.keywords {
+            scaffolder = scaffolder.keywords(keywords.clone());
+        }
+        if let Some(badges) = &self.badges {
+            scaffolder = scaffolder.badges(badges.clone());
+        }
+        // The profile's `template` comes before the default directory
+        let template_dir = match &self.template {
+            Some(dir) => Some(dir.clone()),
+            None if profile_template || !default_templates => None,
+            None => template::default_dir().filter(|dir| dir.is_dir()),
+        };
+        if let Some(dir) = template_dir {
+            scaffolder = scaffolder.template_dir(dir);
+        }
+        Ok(scaffolder)
+    }
+
+    /// The profile file: `--config`, or the default one.
+    fn config_path(&self) -> Option<PathBuf> {
+        self.config.clone().or_else(Profile::path)
+    }
+
+    /// `--explain-profile`: the profile for a crate at `target`, one value
+    /// per line with where it came from.
+    fn explain(&self, target: &Path) -> Result<()> {
+        let profile = Profile::resolve(
+            self.config_path().as_deref(),
+            self.profile.as_deref(),
+            target,
+        )?
+        .unwrap_or_default();
+        for (key, value, origin) in profile.explain() {
+            let (value, origin) = match (key, &self.license) {
+                ("license", Some(license)) => (Some(license.as_str()), "--license".to_string()),
+                _ => (value, origin.map(ToString::to_string).unwrap_or_default()),
+            };
+            match value {
+                Some(value) => println!("{:<13} {:<30} {}", key, value, origin),
+                None => println!("{:<13} {:<30} (unset)", key, "-"),
+            }
         }
+        Ok(())
     }
 }
@@ -0,0 +1,478 @@
+use std::fmt;
+
+use toml_edit::{value, Array, DocumentMut, Item, Table};
+
+/// A Cargo.toml that can't be edited.
+#[derive(Debug)]
+pub enum ManifestError {
+    /// The file isn't valid TOML.
+    Parse(toml_edit::TomlError),
+    /// A key that should hold a table holds something else, e.g.
+    /// `package.metadata = "x"`.
+    NotATable(String),
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::Parse(err) => write!(f, "{}", err),
+            ManifestError::NotATable(key) => write!(f, "`{}` is not a table", key),
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+impl From<toml_edit::TomlError> for ManifestError {
+    fn from(err: toml_edit::TomlError) -> Self {
+        ManifestError::Parse(err)
+    }
+}
+
+/// Package metadata filled in from the profile.
+#[derive(Default)]
+pub struct Metadata {
+    pub authors: Option<String>,
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub repository: Option<String>,
+    pub rust_version: Option<String>,
+    pub keywords: Option<Vec<String>>,
+    pub publish: Option<bool>,
+}
+
+/// Write `metadata` into the `[package]` table of a Cargo.toml document.
+///
+/// The manifest is edited in place, so comments, ordering and any other
+/// tables (like `[dependencies]`) are left untouched. Keys that already
+/// exist are updated instead of duplicated.
+pub fn apply_metadata(contents: &str, metadata: &Metadata) -> Result<String, ManifestError> {
+    edit_metadata(contents, "package", metadata, true).map(|(contents, _)| contents)
+}
+
+/// Like [`apply_metadata`], for the `[workspace.package]` table of a
+/// workspace root, whose values members inherit.
+pub fn apply_workspace_metadata(
+    contents: &str,
+    metadata: &Metadata,
+) -> Result<String, ManifestError> {
+    edit_metadata(contents, "workspace.package", metadata, true).map(|(contents, _)| contents)
+}
+
+/// Like [`apply_metadata`], but only adds keys that are missing. Returns the
+/// new manifest and the keys that were left alone because they already had
+/// a value.
+pub fn fill_metadata(
+    contents: &str,
+    metadata: &Metadata,
+) -> Result<(String, Vec<&'static str>), ManifestError> {
+    edit_metadata(contents, "package", metadata, false)
+}
+
+fn edit_metadata(
+    contents: &str,
+    table: &str,
+    metadata: &Metadata,
+    replace: bool,
+) -> Result<(String, Vec<&'static str>), ManifestError> {
+    let mut doc: DocumentMut = contents.parse()?;
+    let package = table_mut(&mut doc, table)?;
+
+    let mut fields: Vec<(&'static str, Item)> = Vec::new();
+    if let Some(authors) = &metadata.authors {
+        let mut array = Array::new();
+        array.push(authors.as_str());
+        fields.push(("authors", value(array)));
+    }
+    if let Some(description) = &metadata.description {
+        fields.push(("description", value(description.as_str())));
+    }
+    if let Some(license) = &metadata.license {
+        fields.push(("license", value(license.as_str())));
+    }
+    if let Some(repository) = &metadata.repository {
+        fields.push(("repository", value(repository.as_str())));
+    }
+    if let Some(rust_version) = &metadata.rust_version {
+        fields.push(("rust-version", value(rust_version.as_str())));
+    }
+    if let Some(keywords) = &metadata.keywords {
+        let array: Array = keywords.iter().map(String::as_str).collect();
+        fields.push(("keywords", value(array)));
+    }
+    if let Some(publish) = metadata.publish {
+        fields.push(("publish", value(publish)));
+    }
+
+    let mut kept = Vec::new();
+    for (key, item) in fields {
+        if !replace && package.contains_key(key) {
+            kept.push(key);
+        } else {
+            set_key(package, key, item);
+        }
+    }
+
+    Ok((doc.to_string(), kept))
+}
+
+/// Set `(table, key, value)` entries, where `value` is written as TOML
+/// (e.g. `{ version = "1", features = ["full"] }`). Missing tables are
+/// created before `[dependencies]`-style tables so `[lib]` stays near the
+/// top.
+pub fn add_entries(
+    contents: &str,
+    entries: &[(&str, &str, &str)],
+) -> Result<String, ManifestError> {
+    let mut doc: DocumentMut = contents.parse()?;
+
+    for (table_name, key, raw) in entries {
+        let item = format!("value = {}", raw).parse::<DocumentMut>()?["value"].clone();
+        if !table_name.contains('.') && !doc.contains_table(table_name) {
+            let mut table = Table::new();
+            if !table_name.ends_with("dependencies") {
+                if let Some(position) = first_dependencies_position(&doc) {
+                    shift_positions(&mut doc, position);
+                    table.set_position(position);
+                }
+            }
+            doc.insert(table_name, Item::Table(table));
+        }
+        set_key(table_mut(&mut doc, table_name)?, key, item);
+    }
+
+    Ok(doc.to_string())
+}
+
+/// The table at the dotted path `name` (e.g. `workspace.package`), created
+/// if missing. An empty `name` is the document's root table. A value that
+/// is in the way is an error rather than replaced.
+pub(crate) fn table_mut<'a>(
+    doc: &'a mut DocumentMut,
+    name: &str,
+) -> Result<&'a mut Table, ManifestError> {
+    let mut table = doc.as_table_mut();
+    let mut path = Vec::new();
+    for key in name.split('.').filter(|key| !key.is_empty()) {
+        path.push(key);
+        let item = table.entry(key).or_insert_with(|| {
+            let mut table = Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        });
+        table = item
+            .as_table_mut()
+            .ok_or_else(|| ManifestError::NotATable(path.join(".")))?;
+    }
+    Ok(table)
+}
+
+/// Position of the first `[*dependencies]` table in the document.
+fn first_dependencies_position(doc: &DocumentMut) -> Option<usize> {
+    doc.iter()
+        .filter(|(name, _)| name.ends_with("dependencies"))
+        .filter_map(|(_, item)| item.as_table().and_then(Table::position))
+        .min()
+}
+
+/// Move every table at or after `from` one position down.
+fn shift_positions(doc: &mut DocumentMut, from: usize) {
+    for (_, item) in doc.iter_mut() {
+        if let Some(table) = item.as_table_mut() {
+            if let Some(position) = table.position().filter(|&p| p >= from) {
+                table.set_position(position + 1);
+            }
+        }
+    }
+}
+
+/// Fields of the `[package]` table, when set to plain strings (inherited
+/// `license.workspace = true` reads as `None`).
+pub struct PackageInfo {
+    pub name: Option<String>,
+    pub license: Option<String>,
+    pub rust_version: Option<String>,
+}
+
+pub fn package_info(contents: &str) -> Result<PackageInfo, toml_edit::TomlError> {
+    let doc: DocumentMut = contents.parse()?;
+    let field = |key: &str| {
+        doc.get("package")
+            .and_then(|package| package.get(key))
+            .and_then(|item| item.as_str())
+            .map(str::to_string)
+    };
+    Ok(PackageInfo {
+        name: field("name"),
+        license: field("license"),
+        rust_version: field("rust-version"),
+    })
+}
+
+/// Replace the value of `key`, keeping its existing decoration (comments,
+/// whitespace) when the key is already present.
+pub(crate) fn set_key(table: &mut Table, key: &str, mut item: Item) {
+    if let Some(existing) = table.get_mut(key) {
+        if let (Some(old), Some(new)) = (existing.as_value(), item.as_value_mut()) {
+            *new.decor_mut() = old.decor().clone();
+        }
+        *existing = item;
+    } else {
+        table.insert(key, item);
+    }
+}
+
+/// What a workspace root's Cargo.toml says about its members.
+#[derive(Clone, Debug, Default)]
+pub struct WorkspaceInfo {
+    pub members: Vec<String>,
+    pub exclude: Vec<String>,
+    /// Keys of `[workspace.package]`, which members can inherit.
+    pub package_keys: Vec<String>,
+    /// Keys of `[workspace.dependencies]`, which members depend on with
+    /// `{ workspace = true }`.
+    pub dependencies: Vec<String>,
+    pub license: Option<String>,
+    pub repository: Option<String>,
+    pub rust_version: Option<String>,
+}
+
+/// The `[workspace]` table of a Cargo.toml, if it has one.
+pub fn workspace_info(contents: &str) -> Result<Option<WorkspaceInfo>, toml_edit::TomlError> {
+    let doc: DocumentMut = contents.parse()?;
+    let Some(workspace) = doc.get("workspace").and_then(Item::as_table_like) else {
+        return Ok(None);
+    };
+    let strings = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(Item::as_array)
+            .map(|array| {
+                array
+                    .iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let keys = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(Item::as_table_like)
+            .map(|table| table.iter().map(|(key, _)| key.to_string()).collect())
+            .unwrap_or_default()
+    };
+    let package = workspace.get("package").and_then(Item::as_table_like);
+    let package_str = |key: &str| {
+        package
+            .and_then(|package| package.get(key))
+            .and_then(Item::as_str)
+            .map(str::to_string)
+    };
+    Ok(Some(WorkspaceInfo {
+        members: strings("members"),
+        exclude: strings("exclude"),
+        package_keys: keys("package"),
+        dependencies: keys("dependencies"),
+        license: package_str("license"),
+        repository: package_str("repository"),
+        rust_version: package_str("rust-version"),
+    }))
+}
+
+/// Append `member` to `[workspace] members`, creating the array if needed.
+pub fn add_member(contents: &str, member: &str) -> Result<String, ManifestError> {
+    let mut doc: DocumentMut = contents.parse()?;
+    let workspace = doc["workspace"]
+        .as_table_like_mut()
+        .expect("only called on workspace manifests");
+    match workspace.get_mut("members").and_then(Item::as_array_mut) {
+        Some(members) => {
+            // Follow the existing layout: one member per line or all inline
+            let multiline = members
+                .iter()
+                .last()
+                .and_then(|last| last.decor().prefix())
+                .and_then(|prefix| prefix.as_str())
+                .filter(|prefix| prefix.contains('\n'));
+            match multiline {
+                Some(prefix) => {
+                    let prefix = prefix.to_string();
+                    // Without a trailing comma, the newline before `]` is
+                    // the last member's suffix: move it after the new one
+                    let last = members.iter_mut().last().expect("checked above");
+                    let suffix = last
+                        .decor()
+                        .suffix()
+                        .and_then(|suffix| suffix.as_str())
+                        .unwrap_or_default()
+                        .to_string();
+                    last.decor_mut().set_suffix("");
+                    let trailing = members.trailing().as_str().unwrap_or_default();
+                    members.set_trailing(format!("{}{}", suffix, trailing));
+                    members.set_trailing_comma(true);
+
+                    let mut value = toml_edit::Value::from(member);
+                    value.decor_mut().set_prefix(prefix);
+                    members.push_formatted(value);
+                }
+                None => members.push(member),
+            }
+        }
+        None => {
+            let mut members = Array::new();
+            members.push(member);
+            workspace.insert("members", value(members));
+        }
+    }
+    Ok(doc.to_string())
+}
+
+/// Make the `[package]` table inherit `keys` from the workspace, written as
+/// `key.workspace = true`.
+pub fn inherit_keys(contents: &str, keys: &[&str]) -> Result<String, ManifestError> {
+    let mut doc: DocumentMut = contents.parse()?;
+    let package = table_mut(&mut doc, "package")?;
+    for key in keys {
+        // A dotted key prints the decoration of its last value, so a
+        // comment after the old value moves onto `true`
+        let mut flag = toml_edit::Value::from(true);
+        if let Some(old) = package.get(key).and_then(Item::as_value) {
+            *flag.decor_mut() = old.decor().clone();
+        }
+        let mut inherited = toml_edit::InlineTable::new();
+        inherited.insert("workspace", flag);
+        inherited.set_dotted(true);
+        set_key(package, key, value(inherited));
+    }
+    Ok(doc.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"# The crate
+[package]
+name = "x"  # keep me
+version = "0.1.0"
+license = "MIT" # was ISC
+
+[dependencies]
+# Pinned on purpose
+serde = "1"
+"#;
+
+    #[test]
+    fn apply_metadata_keeps_comments_and_other_tables() {
+        let metadata = Metadata {
+            license: Some("Apache-2.0".into()),
+            keywords: Some(vec!["cli".into()]),
+            ..Metadata::default()
+        };
+        assert_eq!(
+            apply_metadata(MANIFEST, &metadata).unwrap(),
+            r#"# The crate
+[package]
+name = "x"  # keep me
+version = "0.1.0"
+license = "Apache-2.0" # was ISC
+keywords = ["cli"]
+
+[dependencies]
+# Pinned on purpose
+serde = "1"
+"#
+        );
+    }
+
+    #[test]
+    fn fill_metadata_only_adds_missing_keys() {
+        let metadata = Metadata {
+            license: Some("Apache-2.0".into()),
+            description: Some("Does one thing".into()),
+            ..Metadata::default()
+        };
+        let (after, kept) = fill_metadata(MANIFEST, &metadata).unwrap();
+        assert_eq!(kept, ["license"]);
+        assert!(after.contains("license = \"MIT\" # was ISC\n"), "{}", after);
+        assert!(
+            after.contains("description = \"Does one thing\"\n"),
+            "{}",
+            after
+        );
+        assert!(
+            after.contains("# Pinned on purpose\nserde = \"1\"\n"),
+            "{}",
+            after
+        );
+    }
+
+    #[test]
+    fn refuses_to_replace_a_value_with_a_table() {
+        let manifest = "[package]\nname = \"x\"\nmetadata = \"x\"\n";
+        let err = add_entries(
+            manifest,
+            &[("package.metadata.docs", "all-features", "true")],
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.to_string(), "`package.metadata` is not a table");
+
+        let err = inherit_keys("package = \"x\"\n", &["edition"])
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "`package` is not a table");
+    }
+
+    #[test]
+    fn add_entries_puts_new_tables_before_dependencies() {
+        let after = add_entries(MANIFEST, &[("lib", "proc-macro", "true")]).unwrap();
+        assert!(
+            after.contains(
+                "license = \"MIT\" # was ISC\n\n[lib]\nproc-macro = true\n\n[dependencies]\n"
+            ),
+            "{}",
+            after
+        );
+    }
+
+    #[test]
+    fn add_member_follows_a_multiline_layout() {
+        let manifest = "[workspace]\n# Our crates\nmembers = [\n    \"a\",\n    \"b\",\n]\n";
+        assert_eq!(
+            add_member(manifest, "crates/c").unwrap(),
+            "[workspace]\n# Our crates\nmembers = [\n    \"a\",\n    \"b\",\n    \"crates/c\",\n]\n"
+        );
+        // Without a trailing comma
+        let manifest = "[workspace]\nmembers = [\n    \"a\",\n    \"b\"\n]\n";
+        assert_eq!(
+            add_member(manifest, "c").unwrap(),
+            "[workspace]\nmembers = [\n    \"a\",\n    \"b\",\n    \"c\",\n]\n"
+        );
+    }
+
+    #[test]
+    fn add_member_keeps_inline_arrays_inline() {
+        let manifest = "[workspace]\nmembers = [\"a\", \"b\"] # all of them\n";
+        assert_eq!(
+            add_member(manifest, "c").unwrap(),
+            "[workspace]\nmembers = [\"a\", \"b\", \"c\"] # all of them\n"
+        );
+        assert_eq!(
+            add_member("[workspace]\nresolver = \"2\"\n", "c").unwrap(),
+            "[workspace]\nresolver = \"2\"\nmembers = [\"c\"]\n"
+        );
+    }
+
+    #[test]
+    fn inherit_keys_replaces_values_in_place() {
+        let after = inherit_keys(MANIFEST, &["license", "edition"]).unwrap();
+        assert!(
+            after.contains("version = \"0.1.0\"\nlicense.workspace = true # was ISC\nedition.workspace = true\n"),
+            "{}",
+            after
+        );
+        assert!(after.starts_with("# The crate\n[package]\n"), "{}", after);
+    }
+}
@@ -0,0 +1,223 @@
+//! Line-based three-way merge for `cargo setup update`.
+
+use std::ops::Range;
+
+use similar::{capture_diff_slices, Algorithm, DiffTag};
+
+/// The result of [`merge`]: the merged text, and how many places both
+/// sides changed differently, which are written between conflict markers.
+pub(crate) struct Merged {
+    pub text: String,
+    pub conflicts: usize,
+}
+
+/// A change one side made to the base: `range` of its lines replaced by
+/// `lines`.
+struct Change<'a> {
+    range: Range<usize>,
+    lines: &'a [&'a str],
+}
+
+/// Merge the changes from `base` to `current` (the user's edits) and from
+/// `base` to `new` (the regenerated file), like `git merge-file`. Where they
+/// overlap and differ, both versions are kept between markers.
+pub(crate) fn merge(base: &str, current: &str, new: &str) -> Merged {
+    let base: Vec<&str> = base.split_inclusive('\n').collect();
+    let current: Vec<&str> = current.split_inclusive('\n').collect();
+    let new: Vec<&str> = new.split_inclusive('\n').collect();
+    let mine = changes(&base, &current);
+    let theirs = changes(&base, &new);
+
+    let mut out = String::new();
+    let mut conflicts = 0;
+    let mut position = 0;
+    let (mut m, mut t) = (0, 0);
+    while m < mine.len() || t < theirs.len() {
+        // The next change, and every change from either side that overlaps
+        // or touches it
+        let first = match (mine.get(m), theirs.get(t)) {
+            (Some(a), Some(b)) => a.range.start.min(b.range.start),
+            (Some(a), None) => a.range.start,
+            (None, Some(b)) => b.range.start,
+            (None, None) => unreachable!(),
+        };
+        let (m_start, t_start) = (m, t);
+        let mut end = first;
+        loop {
+            if let Some(change) = mine.get(m).filter(|c| c.range.start <= end) {
+                end = end.max(change.range.end);
+                m += 1;
+            } else if let Some(change) = theirs.get(t).filter(|c| c.range.start <= end) {
+                end = end.max(change.range.end);
+                t += 1;
+            } else {
+                break;
+            }
+        }
+
+        out.push_str(&base[position..first].concat());
+        let region = first..end;
+        let ours = apply(&base, &mine[m_start..m], region.clone());
+        let new = apply(&base, &theirs[t_start..t], region);
+        if m == m_start {
+            out.push_str(&new);
+        } else if t == t_start || ours == new {
+            out.push_str(&ours);
+        } else {
+            conflicts += 1;
+            out.push_str("<<<<<<< current\n");
+            push_line(&mut out, &ours);
+            out.push_str("=======\n");
+            push_line(&mut out, &new);
+            out.push_str(">>>>>>> cargo setup update\n");
+        }
+        position = end;
+    }
+    out.push_str(&base[position..].concat());
+    Merged {
+        text: out,
+        conflicts,
+    }
+}
+
+/// What changed from `base` to `other`, in order.
+fn changes<'a>(base: &[&str], other: &'a [&'a str]) -> Vec<Change<'a>> {
+    capture_diff_slices(Algorithm::Myers, base, other)
+        .iter()
+        .map(|op| op.as_tag_tuple())
+        .filter(|(tag, _, _)| *tag != DiffTag::Equal)
+        .map(|(_, old, new)| Change {
+            range: old,
+            lines: &other[new],
+        })
+        .collect()
+}
+
+/// The lines of `base` in `region` with `changes` applied.
+fn apply(base: &[&str], changes: &[Change], region: Range<usize>) -> String {
+    let mut out = String::new();
+    let mut position = region.start;
+    for change in changes {
+        out.push_str(&base[position..change.range.start].concat());
+        out.push_str(&change.lines.concat());
+        position = change.range.end;
+    }
+    out.push_str(&base[position..region.end].concat());
+    out
+}
+
+/// Append `text`, ending it with a newline so a marker can follow.
+fn push_line(out: &mut String, text: &str) {
+    out.push_str(text);
+    if !text.is_empty() && !text.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(base: &str, current: &str, new: &str) -> String {
+        let merged = merge(base, current, new);
+        assert_eq!(merged.conflicts, 0, "unexpected conflict:\n{}", merged.text);
+        merged.text
+    }
+
+    #[test]
+    fn disjoint_edits_merge_cleanly() {
+        let base = "a\nb\nc\nd\ne\n";
+        assert_eq!(
+            clean(base, "a\nB\nc\nd\ne\n", "a\nb\nc\nd\nE\n"),
+            "a\nB\nc\nd\nE\n"
+        );
+        // Insertions and deletions on either side
+        assert_eq!(
+            clean(base, "a\nb\nc\nd\ne\nnotes\n", "header\na\nb\nd\ne\n"),
+            "header\na\nb\nd\ne\nnotes\n"
+        );
+    }
+
+    #[test]
+    fn unchanged_sides_take_the_other() {
+        let base = "a\nb\n";
+        assert_eq!(clean(base, base, "a\nc\n"), "a\nc\n");
+        assert_eq!(clean(base, "a\nc\n", base), "a\nc\n");
+        assert_eq!(clean(base, base, base), base);
+    }
+
+    #[test]
+    fn same_line_edits_conflict() {
+        let merged = merge("a\nb\nc\n", "a\nmine\nc\n", "a\ntheirs\nc\n");
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(
+            merged.text,
+            "a\n<<<<<<< current\nmine\n=======\ntheirs\n>>>>>>> cargo setup update\nc\n"
+        );
+    }
+
+    #[test]
+    fn identical_changes_do_not_conflict() {
+        assert_eq!(
+            clean("a\nb\nc\n", "a\nsame\nc\nd\n", "a\nsame\nc\nd\n"),
+            "a\nsame\nc\nd\n"
+        );
+    }
+
+    #[test]
+    fn adjacent_changes_conflict() {
+        // Changes to neighbouring lines touch, so they are one region
+        let merged = merge("a\nb\nc\nd\n", "a\nB\nc\nd\n", "a\nb\nC\nd\n");
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(
+            merged.text,
+            "a\n<<<<<<< current\nB\nc\n=======\nb\nC\n>>>>>>> cargo setup update\nd\n"
+        );
+    }
+
+    #[test]
+    fn touching_insertions_conflict() {
+        // Both sides insert at the same place
+        let merged = merge("a\nb\n", "a\nmine\nb\n", "a\ntheirs\nb\n");
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(
+            merged.text,
+            "a\n<<<<<<< current\nmine\n=======\ntheirs\n>>>>>>> cargo setup update\nb\n"
+        );
+    }
+
+    #[test]
+    fn separate_regions_count_separately() {
+        let base = "a\nb\nc\nd\ne\n";
+        let merged = merge(base, "A1\nb\nc\nd\nE1\n", "A2\nb\nc\nd\nE2\n");
+        assert_eq!(merged.conflicts, 2);
+        assert!(merged.text.contains("\nb\nc\nd\n"));
+    }
+
+    #[test]
+    fn empty_base() {
+        // Without the generated contents, both whole files differ
+        let merged = merge("", "mine\n", "theirs\n");
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(
+            merged.text,
+            "<<<<<<< current\nmine\n=======\ntheirs\n>>>>>>> cargo setup update\n"
+        );
+        assert_eq!(clean("", "same\n", "same\n"), "same\n");
+        assert_eq!(clean("", "", "new\n"), "new\n");
+        assert_eq!(clean("", "", ""), "");
+    }
+
+    #[test]
+    fn missing_trailing_newline() {
+        assert_eq!(clean("a\nb", "a\nb", "a\nb\nc"), "a\nb\nc");
+        assert_eq!(clean("a\nb\nc", "A\nb\nc", "a\nb\nC"), "A\nb\nC");
+        // A conflicting last line still gets its own line before the marker
+        let merged = merge("a\nb", "a\nmine", "a\ntheirs");
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(
+            merged.text,
+            "a\n<<<<<<< current\nmine\n=======\ntheirs\n>>>>>>> cargo setup update\n"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Dry-run output: the planned file tree, then each file's contents or a
+//! unified diff against what is already there.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use similar::TextDiff;
+
+/// A file the scaffold would touch.
+pub struct PlannedFile {
+    pub path: String,
+    /// What the file contains before scaffolding, if it exists.
+    pub before: Option<String>,
+    /// What the file would contain afterwards; `None` if it is left alone.
+    pub after: Option<String>,
+}
+
+impl PlannedFile {
+    fn status(&self) -> &'static str {
+        match (&self.before, &self.after) {
+            (_, None) => "skipped",
+            (None, Some(_)) => "new",
+            (Some(before), Some(after)) if before == after => "unchanged",
+            (Some(_), Some(_)) => "modified",
+        }
+    }
+}
+
+/// Everything a scaffold would do to the crate at `root`, computed without
+/// touching the disk. Its `Display` output is what `--dry-run` prints.
+pub struct Plan {
+    pub root: PathBuf,
+    pub files: Vec<PlannedFile>,
+    /// Files outside `root` that change too, such as the workspace
+    /// manifest. Their paths are shown as they are.
+    pub outside: Vec<PlannedFile>,
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Dry run: nothing will be written.\n")?;
+
+        let mut tree = Node::default();
+        for file in &self.files {
+            let mut node = &mut tree;
+            for part in file.path.split('/') {
+                node = node.children.entry(part.to_string()).or_default();
+            }
+            node.status = Some(file.status());
+        }
+        writeln!(f, "{}/", self.root.display())?;
+        tree.fmt(f, "")?;
+        for file in &self.outside {
+            writeln!(f, "{} ({})", file.path, file.status())?;
+        }
+
+        for file in self.files.iter().chain(&self.outside) {
+            writeln!(f)?;
+            match (&file.before, &file.after) {
+                (None, Some(after)) => {
+                    writeln!(f, "── {} (new) ──", file.path)?;
+                    write!(f, "{}", after)?;
+                    if !after.ends_with('\n') {
+                        writeln!(f)?;
+                    }
+                }
+                (Some(before), Some(after)) if before != after => {
+                    writeln!(f, "── {} (modified) ──", file.path)?;
+                    let diff = TextDiff::from_lines(before, after);
+                    write!(
+                        f,
+                        "{}",
+                        diff.unified_diff()
+                            .header(&format!("a/{}", file.path), &format!("b/{}", file.path))
+                    )?;
+                }
+                _ => writeln!(f, "── {} ({}) ──", file.path, file.status())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Node {
+    children: BTreeMap<String, Node>,
+    status: Option<&'static str>,
+}
+
+impl Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>, prefix: &str) -> fmt::Result {
+        let count = self.children.len();
+        for (i, (name, child)) in self.children.iter().enumerate() {
+            let last = i + 1 == count;
+            let branch = if last { "└── " } else { "├── " };
+            let suffix = if child.children.is_empty() { "" } else { "/" };
+            match child.status {
+                Some(status) if status != "new" => {
+                    writeln!(f, "{}{}{}{} ({})", prefix, branch, name, suffix, status)?
+                }
+                _ => writeln!(f, "{}{}{}{}", prefix, branch, name, suffix)?,
+            }
+            let indent = if last { "    " } else { "│   " };
+            child.fmt(f, &format!("{}{}", prefix, indent))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, before: Option<&str>, after: Option<&str>) -> PlannedFile {
+        PlannedFile {
+            path: path.into(),
+            before: before.map(Into::into),
+            after: after.map(Into::into),
+        }
+    }
+
+    fn plan(files: Vec<PlannedFile>) -> String {
+        Plan {
+            root: PathBuf::from("mycrate"),
+            files,
+            outside: Vec::new(),
+        }
+        .to_string()
+    }
+
+    #[test]
+    fn new_files_are_shown_in_full() {
+        let output = plan(vec![file(
+            "Cargo.toml",
+            None,
+            Some("[package]\nname = \"x\"\n"),
+        )]);
+        assert!(output.contains("mycrate/\n└── Cargo.toml\n"), "{}", output);
+        assert!(
+            output.contains("── Cargo.toml (new) ──\n[package]\nname = \"x\"\n"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn existing_files_are_diffed() {
+        let output = plan(vec![
+            file("README.md", Some("a\n"), Some("a\nb\n")),
+            file("LICENSE", Some("MIT\n"), Some("MIT\n")),
+            file("CHANGELOG.md", Some("mine\n"), None),
+        ]);
+        assert!(
+            output.contains("├── CHANGELOG.md (skipped)\n"),
+            "{}",
+            output
+        );
+        assert!(output.contains("├── LICENSE (unchanged)\n"), "{}", output);
+        assert!(output.contains("└── README.md (modified)\n"), "{}", output);
+        assert!(
+            output.contains("── README.md (modified) ──\n"),
+            "{}",
+            output
+        );
+        assert!(output.contains("\n a\n+b\n"), "{}", output);
+        assert!(output.contains("── LICENSE (unchanged) ──\n"), "{}", output);
+    }
+}
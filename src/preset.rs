@@ -0,0 +1,216 @@
+//! Project presets: a starting point for a particular kind of crate, on top
+//! of the standard extras.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ci::Step;
+use crate::scaffold::Kind;
+use crate::template::Template;
+
+/// A kind of project with its own dependencies, source files, tests and CI
+/// steps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    /// A command-line tool using clap.
+    Cli,
+    /// A long-running async service on tokio.
+    Service,
+    /// A procedural macro crate with syn and quote.
+    #[serde(alias = "proc_macro")]
+    ProcMacro,
+    /// A `#![no_std]` library, built for an embedded target in CI.
+    #[serde(rename = "no_std", alias = "no-std")]
+    NoStd,
+    /// A C-compatible `cdylib` with a cbindgen configuration.
+    Ffi,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 5] = [
+        Preset::Cli,
+        Preset::Service,
+        Preset::ProcMacro,
+        Preset::NoStd,
+        Preset::Ffi,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Preset::Cli => "cli",
+            Preset::Service => "service",
+            Preset::ProcMacro => "proc-macro",
+            Preset::NoStd => "no_std",
+            Preset::Ffi => "ffi",
+        }
+    }
+
+    /// Whether the preset is a binary or a library.
+    pub fn kind(self) -> Kind {
+        match self {
+            Preset::Cli | Preset::Service => Kind::Bin,
+            Preset::ProcMacro | Preset::NoStd | Preset::Ffi => Kind::Lib,
+        }
+    }
+
+    /// The oldest Rust that builds the preset's code, when that is newer
+    /// than any edition needs: `#[unsafe(no_mangle)]` arrived in 1.82.
+    pub fn rust_version(self) -> Option<&'static str> {
+        match self {
+            Preset::Ffi => Some("1.82"),
+            Preset::Cli | Preset::Service | Preset::ProcMacro | Preset::NoStd => None,
+        }
+    }
+
+    /// Cargo.toml entries as `(table, key, value)`, where `value` is a TOML
+    /// value such as `"1"` or `{ version = "1", features = ["full"] }`.
+    pub(crate) fn manifest_entries(self) -> &'static [(&'static str, &'static str, &'static str)] {
+        match self {
+            Preset::Cli => &[(
+                "dependencies",
+                "clap",
+                r#"{ version = "4", features = ["derive"] }"#,
+            )],
+            Preset::Service => &[(
+                "dependencies",
+                "tokio",
+                r#"{ version = "1", features = ["full"] }"#,
+            )],
+            Preset::ProcMacro => &[
+                ("lib", "proc-macro", "true"),
+                ("dependencies", "proc-macro2", r#""1""#),
+                ("dependencies", "quote", r#""1""#),
+                (
+                    "dependencies",
+                    "syn",
+                    r#"{ version = "2", features = ["full"] }"#,
+                ),
+            ],
+            Preset::NoStd => &[],
+            Preset::Ffi => &[("lib", "crate-type", r#"["cdylib", "rlib"]"#)],
+        }
+    }
+
+    /// The preset's source files and tests. They replace what the skeleton
+    /// and the built-in templates would write at the same path.
+    pub(crate) fn templates(self) -> Vec<Template> {
+        let files: &[(&str, &str)] = match self {
+            Preset::Cli => &[
+                (
+                    "src/main.rs",
+                    include_str!("templates/presets/cli/src/main.rs"),
+                ),
+                (
+                    "tests/basic.rs",
+                    include_str!("templates/presets/cli/tests/basic.rs"),
+                ),
+            ],
+            Preset::Service => &[
+                (
+                    "src/main.rs",
+                    include_str!("templates/presets/service/src/main.rs"),
+                ),
+                (
+                    "tests/basic.rs",
+                    include_str!("templates/presets/service/tests/basic.rs"),
+                ),
+            ],
+            Preset::ProcMacro => &[
+                (
+                    "src/lib.rs",
+                    include_str!("templates/presets/proc-macro/src/lib.rs"),
+                ),
+                (
+                    "tests/basic.rs",
+                    include_str!("templates/presets/proc-macro/tests/basic.rs"),
+                ),
+            ],
+            Preset::NoStd => &[
+                (
+                    "src/lib.rs",
+                    include_str!("templates/presets/no_std/src/lib.rs"),
+                ),
+                (
+                    "tests/basic.rs",
+                    include_str!("templates/presets/no_std/tests/basic.rs"),
+                ),
+            ],
+            Preset::Ffi => &[
+                (
+                    "src/lib.rs",
+                    include_str!("templates/presets/ffi/src/lib.rs"),
+                ),
+                (
+                    "tests/basic.rs",
+                    include_str!("templates/presets/ffi/tests/basic.rs"),
+                ),
+                (
+                    "cbindgen.toml",
+                    include_str!("templates/presets/ffi/cbindgen.toml"),
+                ),
+            ],
+        };
+        files
+            .iter()
+            .map(|(path, contents)| Template::new(*path, *contents))
+            .collect()
+    }
+
+    /// Pipeline steps run after the standard build, test, fmt and clippy.
+    pub(crate) fn ci_steps(self) -> Vec<Step> {
+        match self {
+            Preset::Cli => vec![Step::new("Smoke-test the CLI", &["cargo run -- --help"])],
+            Preset::NoStd => vec![Step::new(
+                "Build for a no_std target",
+                &[
+                    "rustup target add thumbv7em-none-eabihf",
+                    "cargo build --target thumbv7em-none-eabihf",
+                ],
+            )
+            .linux_only()],
+            Preset::Ffi => vec![Step::new(
+                "Generate the C header",
+                &[
+                    "cargo install cbindgen --locked",
+                    "cbindgen --config cbindgen.toml --output include/{{crate_ident}}.h",
+                ],
+            )
+            .linux_only()],
+            Preset::Service | Preset::ProcMacro => Vec::new(),
+        }
+    }
+
+    /// The template variable set for this preset, e.g. `preset_no_std`, so
+    /// user templates can add content with `{{#if preset_no_std}}`.
+    pub(crate) fn var(self) -> String {
+        format!("preset_{}", self.as_str().replace('-', "_"))
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.replace('-', "_");
+        Preset::ALL
+            .into_iter()
+            .find(|preset| preset.as_str().replace('-', "_") == normalized)
+            .ok_or_else(|| {
+                let names: Vec<_> = Preset::ALL.iter().map(|p| p.as_str()).collect();
+                format!(
+                    "unknown preset `{}` (expected one of: {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
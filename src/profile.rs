@@ -0,0 +1,986 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use dirs::home_dir;
+use serde::{Deserialize, Serialize};
+use toml_edit::{DocumentMut, Item};
+
+use crate::badge::Badge;
+use crate::ci::{CiProvider, Job, Matrix, RustVersion, Toolchain};
+use crate::error::{Error, Result};
+use crate::license::Expression;
+use crate::manifest;
+use crate::preset::Preset;
+use crate::scaffold::{Kind, Shared};
+use crate::skeleton::Edition;
+use crate::vcs::git_config;
+use crate::vcs::Vcs;
+use crate::workspace::normalize;
+
+/// Personal defaults shared with [cargo-me](https://crates.io/crates/cargo-me).
+///
+/// The file's top-level keys are the default profile. Named profiles in
+/// `[profiles.<name>]` tables override any of them, and are picked by name
+/// or by the `paths` they cover:
+///
+/// ```toml
+/// name = "Jo Doe"
+/// email = "jo@example.com"
+///
+/// [profiles.work]
+/// email = "jo.doe@acme.com"
+/// organization = "Acme"
+/// paths = ["~/work"]
+/// ```
+///
+/// A `.cargo-setup.toml` file in the target directory or any directory
+/// above it takes the same keys as the default profile, and is layered over
+/// it; see [`Profile::resolve`].
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Profile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub github: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+    /// Base URL of your repositories on GitLab, Forgejo, Gitea..., e.g.
+    /// `https://git.example.com/jo`. Used instead of GitHub when CI isn't
+    /// GitHub Actions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forge: Option<String>,
+    /// The Woodpecker CI server, for its status badge.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub woodpecker: Option<String>,
+    /// Binary or library for new crates, unless a preset decides.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Kind>,
+    /// The preset new crates start from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<Preset>,
+    /// The edition of new crates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edition: Option<Edition>,
+    /// Version control for new crates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vcs: Option<Vcs>,
+    /// Create new crates with `cargo new`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_cargo_new: Option<bool>,
+    /// Files a crate inside a workspace generates anyway.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include: Option<Vec<Shared>>,
+    /// crates.io keywords for `[package]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<Vec<String>>,
+    /// Badges at the top of the README.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub badges: Option<Vec<Badge>>,
+    /// Template directory, `~` meaning the home directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// `publish` in `[package]`: `false` keeps crates off crates.io.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publish: Option<bool>,
+    /// Text at the top of every new `.rs` file, comment markers included.
+    /// Template variables like `{{license}}` and `{{copyright_holder}}` are
+    /// filled in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license_header: Option<String>,
+    /// The `[ci]` table: provider, OSes, toolchains, MSRV, targets and jobs
+    /// for the pipeline.
+    #[serde(default, skip_serializing_if = "Matrix::is_unset")]
+    pub ci: Matrix,
+    /// The file this profile was loaded from, if any.
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+    /// The named profile applied over the defaults, if any.
+    #[serde(skip)]
+    pub selected: Option<String>,
+    /// Where each field that is set came from, by key (`ci.os` for the
+    /// `[ci]` table's).
+    #[serde(skip)]
+    pub origins: BTreeMap<String, Origin>,
+}
+
+/// Where a profile value came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Origin {
+    /// The profile file, and the named profile in it that set the value,
+    /// or a `.cargo-setup.toml` file.
+    File {
+        path: PathBuf,
+        profile: Option<String>,
+    },
+    /// An environment variable such as `CARGO_SETUP_AUTHOR`.
+    Env(&'static str),
+    /// A `git config` key such as `user.name`.
+    Git(&'static str),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::File {
+                path,
+                profile: Some(name),
+            } => write!(f, "{} [profiles.{}]", path.display(), name),
+            Origin::File {
+                path,
+                profile: None,
+            } => write!(f, "{}", path.display()),
+            Origin::Env(var) => write!(f, "environment variable {}", var),
+            Origin::Git(key) => write!(f, "git config {}", key),
+        }
+    }
+}
+
+/// Fields that can also come from the environment, which overrides the
+/// profile file, or from git's configuration, which fills in for it.
+const FALLBACKS: &[(&str, &str, Option<&str>)] = &[
+    ("name", "CARGO_SETUP_AUTHOR", Some("user.name")),
+    ("email", "CARGO_SETUP_EMAIL", Some("user.email")),
+    ("github", "CARGO_SETUP_GITHUB", Some("github.user")),
+    ("license", "CARGO_SETUP_LICENSE", None),
+    ("organization", "CARGO_SETUP_ORGANIZATION", None),
+];
+
+/// The project file layered over the profile, found in the target directory
+/// or above.
+pub const PROJECT_FILE: &str = ".cargo-setup.toml";
+
+/// The profile file: the default profile and the named ones.
+#[derive(Deserialize)]
+struct ProfileFile {
+    #[serde(flatten)]
+    default: Profile,
+    #[serde(default)]
+    profiles: BTreeMap<String, NamedProfile>,
+}
+
+#[derive(Deserialize)]
+struct NamedProfile {
+    #[serde(flatten)]
+    profile: Profile,
+    /// Directories whose crates use this profile, `~` meaning the home
+    /// directory.
+    #[serde(default)]
+    paths: Vec<String>,
+}
+
+impl Profile {
+    /// Where the profile file is by default: `$CARGO_SETUP_CONFIG` if set,
+    /// else `cargo-setup/config.toml` in the XDG config directory, unless
+    /// only cargo-me's `~/.cargo-me.toml` exists. `None` when there is
+    /// neither a home directory nor `XDG_CONFIG_HOME` to put it in.
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("CARGO_SETUP_CONFIG").filter(|path| !path.is_empty()) {
+            return Some(PathBuf::from(path));
+        }
+        let config = config_dir().map(|dir| dir.join("config.toml"));
+        let legacy = home_dir().map(|home| home.join(".cargo-me.toml"));
+        match (config, legacy) {
+            (Some(config), _) if config.exists() => Some(config),
+            (_, Some(legacy)) if legacy.exists() => Some(legacy),
+            (config, legacy) => config.or(legacy),
+        }
+    }
+
+    /// Load the default profile from the default [`Profile::path`], or
+    /// `None` if there is no profile file. A file that exists but can't be
+    /// read or parsed is an error.
+    ///
+    /// This is the file alone: named profiles, project files, the
+    /// environment and git aren't consulted. [`Profile::resolve`] applies
+    /// them the way `cargo setup` does.
+    pub fn load() -> Result<Option<Self>> {
+        Ok(Self::read(Self::path().as_deref())?.map(|(path, file)| {
+            let mut profile = file.default;
+            profile.record_origins(&path, None);
+            profile.source = Some(path);
+            profile
+        }))
+    }
+
+    /// Like [`Profile::select`], with project files, the environment and
+    /// git filling in around the profile file. In order of precedence:
+    ///
+    /// 1. `CARGO_SETUP_AUTHOR`, `CARGO_SETUP_EMAIL`, `CARGO_SETUP_GITHUB`,
+    ///    `CARGO_SETUP_LICENSE` and `CARGO_SETUP_ORGANIZATION`
+    /// 2. the [`PROJECT_FILE`]s from [`Profile::project_files`], the one
+    ///    closest to `target` first
+    /// 3. the selected named profile, then the default profile
+    /// 4. `git config` `user.name`, `user.email` and `github.user`, as seen
+    ///    from `target` (or the nearest directory above it that exists)
+    ///
+    /// `None` when none of them sets anything.
+    pub fn resolve(
+        config: Option<&Path>,
+        name: Option<&str>,
+        target: &Path,
+    ) -> Result<Option<Self>> {
+        let found = Self::select(config, name, target)?;
+        let mut profile = found.clone().unwrap_or_default();
+        // Like cargo's `.cargo/config.toml`: the farthest file first, so
+        // closer ones win
+        for path in Self::project_files(target).iter().rev() {
+            let contents = fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+            let mut project: Profile =
+                toml::from_str(&contents).map_err(|source| Error::Profile {
+                    path: path.clone(),
+                    source,
+                })?;
+            project.record_origins(path, None);
+            profile = project.or(&profile);
+        }
+        let dir = existing_dir(target);
+        profile.apply_fallbacks(
+            |var| std::env::var(var).ok(),
+            |git_key| git_config(&dir, git_key),
+        );
+        if found.is_none() && profile.origins.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(profile))
+    }
+
+    /// Apply the [`FALLBACKS`]: the environment variables, looked up with
+    /// `env`, over the profile, and the `git config` keys, looked up with
+    /// `git`, where the profile has no value.
+    fn apply_fallbacks(
+        &mut self,
+        env: impl Fn(&str) -> Option<String>,
+        git: impl Fn(&str) -> Option<String>,
+    ) {
+        for &(key, var, git_key) in FALLBACKS {
+            let env = env(var).filter(|value| !value.trim().is_empty());
+            let (value, origin) = match (env, git_key) {
+                (Some(value), _) => (value, Origin::Env(var)),
+                (None, Some(git_key)) if self.field(key).is_none() => match git(git_key) {
+                    Some(value) => (value, Origin::Git(git_key)),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            *self.field_mut(key) = Some(value);
+            self.origins.insert(key.to_string(), origin);
+        }
+    }
+
+    /// The [`PROJECT_FILE`]s in the directory of a crate at `target` and
+    /// the directories above it, the closest first. `target` need not exist
+    /// yet.
+    pub fn project_files(target: &Path) -> Vec<PathBuf> {
+        existing_dir(target)
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_FILE))
+            .filter(|path| path.is_file())
+            .collect()
+    }
+
+    /// Load the profile for a crate at `target` from the profile file
+    /// `config`: the named profile `name` if given, else the one whose
+    /// `paths` contain `target` (the longest match wins), over the
+    /// defaults. Naming a profile that doesn't exist is an error.
+    pub fn select(
+        config: Option<&Path>,
+        name: Option<&str>,
+        target: &Path,
+    ) -> Result<Option<Self>> {
+        let Some((path, mut file)) = Self::read(config)? else {
+            return match name {
+                Some(name) => Err(Error::UnknownProfile {
+                    name: name.to_string(),
+                    path: config.map(Path::to_path_buf).unwrap_or_default(),
+                }),
+                None => Ok(None),
+            };
+        };
+
+        let name = match name {
+            Some(name) if file.profiles.contains_key(name) => Some(name.to_string()),
+            Some(name) => {
+                return Err(Error::UnknownProfile {
+                    name: name.to_string(),
+                    path,
+                })
+            }
+            None => {
+                let target = std::path::absolute(target).unwrap_or_else(|_| target.to_path_buf());
+                let target = normalize(&target);
+                file.profiles
+                    .iter()
+                    .flat_map(|(name, named)| named.paths.iter().map(move |dir| (name, dir)))
+                    // A relative or empty path (`~` without a home
+                    // directory) would match everything or nothing
+                    .filter_map(|(name, dir)| Some((name, expand_home(dir)?)))
+                    .filter(|(_, dir)| dir.is_absolute() && target.starts_with(dir))
+                    .max_by_key(|(_, dir)| dir.components().count())
+                    .map(|(name, _)| name.clone())
+            }
+        };
+
+        file.default.record_origins(&path, None);
+        let mut profile = match &name {
+            Some(name) => {
+                let mut named = file.profiles.remove(name).expect("checked above").profile;
+                named.record_origins(&path, Some(name));
+                named.or(&file.default)
+            }
+            None => file.default,
+        };
+        profile.source = Some(path);
+        profile.selected = name;
+        Ok(Some(profile))
+    }
+
+    /// Read and parse the profile file at `path`, if there is one.
+    fn read(path: Option<&Path>) -> Result<Option<(PathBuf, ProfileFile)>> {
+        let Some(path) = path.filter(|path| path.exists()) else {
+            return Ok(None);
+        };
+        let path = path.to_path_buf();
+        let contents = fs::read_to_string(&path).map_err(|err| Error::io(&path, err))?;
+        let file: ProfileFile = toml::from_str(&contents).map_err(|source| Error::Profile {
+            path: path.clone(),
+            source,
+        })?;
+        Ok(Some((path, file)))
+    }
+
+    /// `self`, with the fields it leaves unset taken from `fallback`.
+    fn or(self, fallback: &Profile) -> Profile {
+        let or =
+            |value: Option<String>, fallback: &Option<String>| value.or_else(|| fallback.clone());
+        Profile {
+            name: or(self.name, &fallback.name),
+            email: or(self.email, &fallback.email),
+            github: or(self.github, &fallback.github),
+            license: or(self.license, &fallback.license),
+            organization: or(self.organization, &fallback.organization),
+            forge: or(self.forge, &fallback.forge),
+            woodpecker: or(self.woodpecker, &fallback.woodpecker),
+            kind: self.kind.or(fallback.kind),
+            preset: self.preset.or(fallback.preset),
+            edition: self.edition.or(fallback.edition),
+            vcs: self.vcs.or(fallback.vcs),
+            use_cargo_new: self.use_cargo_new.or(fallback.use_cargo_new),
+            include: self.include.or_else(|| fallback.include.clone()),
+            keywords: self.keywords.or_else(|| fallback.keywords.clone()),
+            badges: self.badges.or_else(|| fallback.badges.clone()),
+            template: or(self.template, &fallback.template),
+            publish: self.publish.or(fallback.publish),
+            license_header: or(self.license_header, &fallback.license_header),
+            ci: self.ci.or(&fallback.ci),
+            source: self.source.or_else(|| fallback.source.clone()),
+            selected: self.selected.or_else(|| fallback.selected.clone()),
+            origins: fallback
+                .origins
+                .iter()
+                .map(|(key, origin)| (key.clone(), origin.clone()))
+                .chain(self.origins)
+                .collect(),
+        }
+    }
+
+    /// The string fields, by key.
+    fn fields(&self) -> [(&'static str, &Option<String>); 7] {
+        [
+            ("name", &self.name),
+            ("email", &self.email),
+            ("github", &self.github),
+            ("license", &self.license),
+            ("organization", &self.organization),
+            ("forge", &self.forge),
+            ("woodpecker", &self.woodpecker),
+        ]
+    }
+
+    /// The string field `key`, which must be one of [`Profile::fields`].
+    fn field(&self, key: &str) -> &Option<String> {
+        self.fields()
+            .into_iter()
+            .find(|(known, _)| *known == key)
+            .map(|(_, value)| value)
+            .expect("a profile field")
+    }
+
+    fn field_mut(&mut self, key: &str) -> &mut Option<String> {
+        match key {
+            "name" => &mut self.name,
+            "email" => &mut self.email,
+            "github" => &mut self.github,
+            "license" => &mut self.license,
+            "organization" => &mut self.organization,
+            "forge" => &mut self.forge,
+            "woodpecker" => &mut self.woodpecker,
+            _ => unreachable!("not a profile field: {}", key),
+        }
+    }
+
+    /// Note that the fields which are set come from `path`.
+    fn record_origins(&mut self, path: &Path, name: Option<&str>) {
+        for (key, _) in self.values() {
+            self.origins.insert(
+                key,
+                Origin::File {
+                    path: path.to_path_buf(),
+                    profile: name.map(str::to_string),
+                },
+            );
+        }
+    }
+
+    /// Each field that is set, as its key in [`KEYS`] order and its value
+    /// as TOML. The `[ci]` table's fields are keyed `ci.<field>`.
+    pub fn values(&self) -> Vec<(String, toml::Value)> {
+        let toml::Value::Table(table) =
+            toml::Value::try_from(self).expect("a profile is always representable as TOML")
+        else {
+            unreachable!("a profile serializes to a table")
+        };
+        let mut values = Vec::new();
+        for (key, value) in table {
+            match value {
+                toml::Value::Table(table) => values.extend(
+                    table
+                        .into_iter()
+                        .map(|(field, value)| (format!("{}.{}", key, field), value)),
+                ),
+                value => values.push((key, value)),
+            }
+        }
+        values.sort_by_key(|(key, _)| KEYS.iter().position(|(known, _)| known == key));
+        values
+    }
+
+    /// Each string field with its value and where that came from, for
+    /// `--explain-profile`.
+    pub fn explain(&self) -> Vec<(&'static str, Option<&str>, Option<&Origin>)> {
+        self.fields()
+            .into_iter()
+            .map(|(key, value)| (key, value.as_deref(), self.origins.get(key)))
+            .collect()
+    }
+
+    /// What is wrong with the values that are set: malformed emails,
+    /// GitHub handles, URLs and keywords, and licenses that aren't SPDX
+    /// expressions.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = self
+            .fields()
+            .into_iter()
+            .filter_map(|(key, value)| check(key, value.as_deref()?).err())
+            .collect();
+        if let Some(keywords) = &self.keywords {
+            let keywords: Vec<&str> = keywords.iter().map(String::as_str).collect();
+            problems.extend(check_keywords(&keywords).err());
+        }
+        problems
+    }
+
+    /// `Name <email>` for the `authors` field, if a name is set.
+    pub fn author(&self) -> Option<String> {
+        self.name.as_ref().map(|name| match &self.email {
+            Some(email) => format!("{} <{}>", name, email),
+            None => name.clone(),
+        })
+    }
+}
+
+/// `cargo-setup` in `$XDG_CONFIG_HOME`, or in `~/.config` when that isn't
+/// set (or isn't an absolute path, which XDG says to ignore).
+pub(crate) fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| Some(home_dir()?.join(".config")))
+        .map(|dir| dir.join("cargo-setup"))
+}
+
+/// `target`, or the nearest directory above it that exists.
+fn existing_dir(target: &Path) -> PathBuf {
+    let target = std::path::absolute(target).unwrap_or_else(|_| target.to_path_buf());
+    normalize(&target)
+        .ancestors()
+        .find(|dir| dir.is_dir())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// `dir` with a leading `~` replaced by the home directory, or `None` if
+/// there is no home directory to replace it with.
+pub(crate) fn expand_home(dir: &str) -> Option<PathBuf> {
+    expand_tilde(dir, home_dir)
+}
+
+fn expand_tilde(dir: &str, home: impl FnOnce() -> Option<PathBuf>) -> Option<PathBuf> {
+    match dir.strip_prefix('~') {
+        Some("") => home(),
+        Some(rest) if rest.starts_with('/') => Some(home()?.join(&rest[1..])),
+        _ => Some(PathBuf::from(dir)),
+    }
+}
+
+/// Keys `cargo setup profile set` knows, and the TOML type of their value.
+const KEYS: &[(&str, Value)] = &[
+    ("name", Value::String),
+    ("email", Value::String),
+    ("github", Value::String),
+    ("license", Value::String),
+    ("organization", Value::String),
+    ("forge", Value::String),
+    ("woodpecker", Value::String),
+    ("kind", Value::String),
+    ("preset", Value::String),
+    ("edition", Value::String),
+    ("vcs", Value::String),
+    ("use-cargo-new", Value::Bool),
+    ("include", Value::List),
+    ("keywords", Value::List),
+    ("badges", Value::List),
+    ("template", Value::String),
+    ("publish", Value::Bool),
+    ("license-header", Value::String),
+    ("paths", Value::List),
+    ("ci.provider", Value::String),
+    ("ci.os", Value::List),
+    ("ci.toolchains", Value::List),
+    ("ci.msrv", Value::String),
+    ("ci.targets", Value::List),
+    ("ci.jobs", Value::List),
+    ("ci.release", Value::Bool),
+    ("ci.release-targets", Value::List),
+];
+
+#[derive(Clone, Copy)]
+enum Value {
+    String,
+    /// Written as a comma-separated list on the command line.
+    List,
+    Bool,
+}
+
+/// Check the value of a profile field, returning why it is rejected.
+pub(crate) fn check(key: &str, value: &str) -> Result<(), String> {
+    let valid = match key {
+        "email" => {
+            let (local, domain) = value.split_once('@').unwrap_or_default();
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !domain.contains('@')
+                && !value.contains(char::is_whitespace)
+        }
+        // GitHub's rules: up to 39 letters, digits and single hyphens
+        "github" => {
+            (1..=39).contains(&value.len())
+                && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                && !value.starts_with('-')
+                && !value.ends_with('-')
+                && !value.contains("--")
+        }
+        "license" => {
+            return Expression::parse(value)
+                .map(|_| ())
+                .map_err(|err| format!("`license`: {}", err))
+        }
+        "forge" | "woodpecker" => value.starts_with("https://") || value.starts_with("http://"),
+        "kind" => return parsed::<Kind>(key, value),
+        "preset" => return parsed::<Preset>(key, value),
+        "edition" => return parsed::<Edition>(key, value),
+        "vcs" => return parsed::<Vcs>(key, value),
+        "include" => return elements(value).try_for_each(|e| parsed::<Shared>(key, e)),
+        "badges" => return elements(value).try_for_each(|e| parsed::<Badge>(key, e)),
+        "keywords" => return check_keywords(&elements(value).collect::<Vec<_>>()),
+        "ci.provider" => return parsed::<CiProvider>(key, value),
+        "ci.msrv" => return parsed::<RustVersion>(key, value),
+        "ci.toolchains" => return elements(value).try_for_each(|e| parsed::<Toolchain>(key, e)),
+        "ci.jobs" => return elements(value).try_for_each(|e| parsed::<Job>(key, e)),
+        _ => true,
+    };
+    if valid {
+        Ok(())
+    } else {
+        let expected = match key {
+            "email" => "an email address",
+            "github" => "a GitHub username",
+            _ => "an http(s) URL",
+        };
+        Err(format!("`{}` is not {}: `{}`", key, expected, value))
+    }
+}
+
+/// crates.io's rules: at most five keywords of up to 20 ASCII letters,
+/// digits, `_`, `-` and `+`, starting with a letter.
+pub(crate) fn check_keywords(keywords: &[&str]) -> Result<(), String> {
+    if keywords.len() > 5 {
+        return Err(format!(
+            "`keywords` has {} entries; crates.io allows 5",
+            keywords.len()
+        ));
+    }
+    for keyword in keywords {
+        let valid = (1..=20).contains(&keyword.len())
+            && keyword.starts_with(|c: char| c.is_ascii_alphabetic())
+            && keyword
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+'));
+        if !valid {
+            return Err(format!("`keywords` has an invalid keyword: `{}`", keyword));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn parsed<T: FromStr<Err = String>>(key: &str, value: &str) -> Result<(), String> {
+    value
+        .parse::<T>()
+        .map(|_| ())
+        .map_err(|err| format!("`{}`: {}", key, err))
+}
+
+/// The elements of a comma-separated list from the command line.
+pub(crate) fn elements(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').map(str::trim).filter(|e| !e.is_empty())
+}
+
+/// The table holding the default profile, or the named profile `name`.
+fn profile_table(name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("profiles.{}", name),
+        None => String::new(),
+    }
+}
+
+/// Edits to the profile file that keep everything else in it: keys this
+/// crate doesn't know (cargo-me's own), comments and layout.
+impl Profile {
+    /// Set `key` (e.g. `email` or `ci.toolchains`) to `value` in the
+    /// default profile or the named profile `name` of the profile file at
+    /// `path`, creating it if needed. Lists are given comma-separated. The
+    /// value is checked before anything is written.
+    pub fn set(path: &Path, key: &str, value: &str, name: Option<&str>) -> Result<()> {
+        let invalid = |problem: String| Error::InvalidProfile {
+            path: path.to_path_buf(),
+            problems: vec![problem],
+        };
+        let kind = KEYS
+            .iter()
+            .find(|(known, _)| *known == key)
+            .map(|&(_, kind)| kind)
+            .ok_or_else(|| invalid(unknown_key(key)))?;
+        if key == "paths" && name.is_none() {
+            return Err(invalid(
+                "`paths` only applies to named profiles (use --profile)".into(),
+            ));
+        }
+        check(key, value).map_err(invalid)?;
+
+        let item = match kind {
+            Value::String => toml_edit::value(value),
+            Value::Bool => toml_edit::value(
+                value
+                    .parse::<bool>()
+                    .map_err(|_| invalid(format!("`{}` must be true or false", key)))?,
+            ),
+            Value::List => {
+                let mut array = toml_edit::Array::new();
+                for element in elements(value) {
+                    array.push(element);
+                }
+                toml_edit::value(array)
+            }
+        };
+
+        let contents = Self::read_raw(path)?;
+        let mut doc = Self::parse_raw(path, &contents)?;
+        let (table, key) = match key.rsplit_once('.') {
+            Some((table, key)) => (join(&profile_table(name), table), key),
+            None => (profile_table(name), key),
+        };
+        let table =
+            manifest::table_mut(&mut doc, &table).map_err(|err| invalid(err.to_string()))?;
+        manifest::set_key(table, key, item);
+        Self::write_raw(path, &doc)
+    }
+
+    /// Remove `key` from the default profile or the named profile `name`
+    /// of the profile file at `path`. Returns whether it was set.
+    pub fn unset(path: &Path, key: &str, name: Option<&str>) -> Result<bool> {
+        if !KEYS.iter().any(|(known, _)| *known == key) {
+            return Err(Error::InvalidProfile {
+                path: path.to_path_buf(),
+                problems: vec![unknown_key(key)],
+            });
+        }
+        if !path.exists() {
+            return Ok(false);
+        }
+        let contents = Self::read_raw(path)?;
+        let mut doc = Self::parse_raw(path, &contents)?;
+        let full = join(&profile_table(name), key);
+        let (table, key) = full.rsplit_once('.').unwrap_or(("", &full));
+        let mut current = Some(doc.as_item_mut());
+        for part in table.split('.').filter(|part| !part.is_empty()) {
+            current = current.and_then(|item| item.get_mut(part));
+        }
+        let removed = current
+            .and_then(Item::as_table_like_mut)
+            .and_then(|table| table.remove(key))
+            .is_some();
+        if removed {
+            Self::write_raw(path, &doc)?;
+        }
+        Ok(removed)
+    }
+
+    /// Write a new profile file at `path` with the non-empty `fields`
+    /// (`(key, value)` pairs from [`KEYS`]), refusing to replace an
+    /// existing one unless `force` is set.
+    pub fn init(path: &Path, fields: &[(&str, String)], force: bool) -> Result<()> {
+        if path.exists() && !force {
+            return Err(Error::AlreadyExists(path.to_path_buf()));
+        }
+        let mut problems = Vec::new();
+        let mut doc = DocumentMut::new();
+        for (key, value) in fields.iter().filter(|(_, value)| !value.is_empty()) {
+            match check(key, value) {
+                Ok(()) => {
+                    doc.insert(key, toml_edit::value(value.as_str()));
+                }
+                Err(problem) => problems.push(problem),
+            }
+        }
+        if !problems.is_empty() {
+            return Err(Error::InvalidProfile {
+                path: path.to_path_buf(),
+                problems,
+            });
+        }
+        Self::write_raw(path, &doc)
+    }
+
+    /// Check the whole profile file at `path`: it must parse, and every
+    /// profile in it must pass [`Profile::problems`]. Returns whether there
+    /// is a file to check.
+    pub fn validate(path: &Path) -> Result<bool> {
+        let Some((path, file)) = Self::read(Some(path))? else {
+            return Ok(false);
+        };
+        let mut problems = file.default.problems();
+        for (name, named) in &file.profiles {
+            problems.extend(
+                named
+                    .profile
+                    .problems()
+                    .into_iter()
+                    .map(|problem| format!("[profiles.{}] {}", name, problem)),
+            );
+        }
+        if problems.is_empty() {
+            Ok(true)
+        } else {
+            Err(Error::InvalidProfile { path, problems })
+        }
+    }
+
+    /// The profile as TOML, the way [`Profile::select`] resolved it.
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("a profile is always representable as TOML")
+    }
+
+    fn read_raw(path: &Path) -> Result<String> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(contents),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+            Err(err) => Err(Error::io(path, err)),
+        }
+    }
+
+    fn parse_raw(path: &Path, contents: &str) -> Result<DocumentMut> {
+        contents
+            .parse()
+            .map_err(|err: toml_edit::TomlError| Error::InvalidProfile {
+                path: path.to_path_buf(),
+                problems: vec![err.to_string()],
+            })
+    }
+
+    /// Write `doc` to `path` if it is still a valid profile file.
+    fn write_raw(path: &Path, doc: &DocumentMut) -> Result<()> {
+        let contents = doc.to_string();
+        toml::from_str::<ProfileFile>(&contents).map_err(|source| Error::Profile {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| Error::io(dir, err))?;
+        }
+        fs::write(path, contents).map_err(|err| Error::io(path, err))
+    }
+}
+
+fn unknown_key(key: &str) -> String {
+    let keys: Vec<&str> = KEYS.iter().map(|(key, _)| *key).collect();
+    format!(
+        "unknown key `{}` (expected one of: {})",
+        key,
+        keys.join(", ")
+    )
+}
+
+/// Join dotted table paths, either of which may be empty.
+fn join(table: &str, key: &str) -> String {
+    match (table.is_empty(), key.is_empty()) {
+        (true, _) => key.to_string(),
+        (_, true) => table.to_string(),
+        _ => format!("{}.{}", table, key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A profile file in a fresh directory, and the directory.
+    fn config(contents: &str) -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, contents).unwrap();
+        (dir, path)
+    }
+
+    fn origin(path: &Path, profile: Option<&str>) -> Origin {
+        Origin::File {
+            path: path.to_path_buf(),
+            profile: profile.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn expands_a_leading_tilde_only() {
+        let home = || Some(PathBuf::from("/home/jo"));
+        assert_eq!(expand_tilde("~", home), Some(PathBuf::from("/home/jo")));
+        assert_eq!(
+            expand_tilde("~/work", home),
+            Some(PathBuf::from("/home/jo/work"))
+        );
+        assert_eq!(expand_tilde("~work", home), Some(PathBuf::from("~work")));
+        assert_eq!(expand_tilde("/srv/~", home), Some(PathBuf::from("/srv/~")));
+        assert_eq!(expand_tilde("~/work", || None), None);
+        assert_eq!(expand_tilde("~", || None), None);
+    }
+
+    #[test]
+    fn selects_the_named_profile_over_the_defaults() {
+        let (_dir, path) =
+            config("name = \"Jo\"\nedition = \"2021\"\n\n[profiles.work]\nedition = \"2024\"\n");
+        let profile = Profile::select(Some(&path), Some("work"), Path::new("x"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(profile.selected.as_deref(), Some("work"));
+        assert_eq!(profile.name.as_deref(), Some("Jo"));
+        assert_eq!(profile.edition, Some(Edition::E2024));
+        assert_eq!(profile.origins["name"], origin(&path, None));
+        assert_eq!(profile.origins["edition"], origin(&path, Some("work")));
+
+        let err = Profile::select(Some(&path), Some("home"), Path::new("x"))
+            .err()
+            .unwrap();
+        assert!(matches!(err, Error::UnknownProfile { .. }), "{}", err);
+    }
+
+    #[test]
+    fn selects_the_most_specific_path() {
+        let (dir, path) = config("");
+        let root = dir.path().display();
+        fs::write(
+            &path,
+            format!(
+                "[profiles.work]\npaths = [\"{root}/work\"]\n\n\
+                 [profiles.oss]\npaths = [\"{root}/work/oss\", \"{root}/oss\"]\n\n\
+                 [profiles.everything]\npaths = [\"\", \"relative\"]\n"
+            ),
+        )
+        .unwrap();
+        let selected = |target: &str| {
+            Profile::select(Some(&path), None, &dir.path().join(target))
+                .unwrap()
+                .unwrap()
+                .selected
+        };
+        assert_eq!(selected("work/tool").as_deref(), Some("work"));
+        assert_eq!(selected("work/oss/tool").as_deref(), Some("oss"));
+        assert_eq!(selected("work/../oss/tool").as_deref(), Some("oss"));
+        assert_eq!(selected("workshop/tool"), None);
+        assert_eq!(selected("tool"), None);
+    }
+
+    #[test]
+    fn closer_project_files_win() {
+        let (dir, path) =
+            config("kind = \"bin\"\nedition = \"2018\"\nforge = \"https://a.example\"\n");
+        let outer = dir.path().join("repo");
+        let inner = outer.join("crates");
+        fs::create_dir_all(&inner).unwrap();
+        fs::write(
+            outer.join(PROJECT_FILE),
+            "edition = \"2021\"\npublish = false\n",
+        )
+        .unwrap();
+        fs::write(inner.join(PROJECT_FILE), "edition = \"2024\"\n").unwrap();
+
+        let profile = Profile::resolve(Some(&path), None, &inner.join("new"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(profile.kind, Some(Kind::Bin));
+        assert_eq!(profile.edition, Some(Edition::E2024));
+        assert_eq!(profile.publish, Some(false));
+        assert_eq!(profile.origins["forge"], origin(&path, None));
+        assert_eq!(
+            profile.origins["edition"],
+            origin(&inner.join(PROJECT_FILE), None)
+        );
+        assert_eq!(
+            profile.origins["publish"],
+            origin(&outer.join(PROJECT_FILE), None)
+        );
+    }
+
+    #[test]
+    fn environment_overrides_and_git_fills_in() {
+        let mut profile = Profile {
+            name: Some("Jo".into()),
+            email: Some("jo@example.com".into()),
+            ..Profile::default()
+        };
+        let env = |var: &str| match var {
+            "CARGO_SETUP_EMAIL" => Some("jo@work.example".to_string()),
+            "CARGO_SETUP_ORGANIZATION" => Some("  ".to_string()),
+            _ => None,
+        };
+        let git = |key: &str| match key {
+            "user.name" => Some("Git Jo".to_string()),
+            "github.user" => Some("jo-git".to_string()),
+            _ => None,
+        };
+        profile.apply_fallbacks(env, git);
+        assert_eq!(profile.name.as_deref(), Some("Jo"));
+        assert_eq!(profile.email.as_deref(), Some("jo@work.example"));
+        assert_eq!(profile.github.as_deref(), Some("jo-git"));
+        assert_eq!(profile.organization, None);
+        assert_eq!(profile.origins.get("name"), None);
+        assert_eq!(profile.origins["email"], Origin::Env("CARGO_SETUP_EMAIL"));
+        assert_eq!(profile.origins["github"], Origin::Git("github.user"));
+    }
+}
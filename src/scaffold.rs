@@ -0,0 +1,2049 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+
+use crate::apply;
+use crate::badge::Badge;
+use crate::ci::{self, CiProvider, Job, Matrix, RustVersion};
+use crate::error::{Error, Result};
+use crate::license::Expression;
+use crate::lock::{self, Lock, LOCK_FILE};
+use crate::manifest::{self, Metadata};
+use crate::merge;
+use crate::plan::{Plan, PlannedFile};
+use crate::preset::Preset;
+use crate::profile::{self, Origin, Profile};
+use crate::skeleton::{self, Edition};
+use crate::template::{self, RenderedFile, Template, TemplateError, Vars};
+use crate::transaction::{self, Transaction};
+use crate::vcs::{self, Vcs};
+use crate::workspace::Workspace;
+
+/// Whether the crate is a binary or a library.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Bin,
+    #[default]
+    Lib,
+}
+
+impl Kind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Bin => "bin",
+            Kind::Lib => "lib",
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bin" => Ok(Kind::Bin),
+            "lib" => Ok(Kind::Lib),
+            _ => Err(format!(
+                "unknown crate kind `{}` (expected `bin` or `lib`)",
+                s
+            )),
+        }
+    }
+}
+
+/// Scaffolds a crate: either a new one (like `cargo new` plus extras) or
+/// the missing pieces of an existing one.
+///
+/// ```no_run
+/// use cargo_setup::{Kind, Profile, Scaffolder};
+///
+/// let report = Scaffolder::new("my-tool")
+///     .kind(Kind::Bin)
+///     .license("MIT OR Apache-2.0")
+///     .profile(Profile::resolve(None, None, "crates/my-tool".as_ref())?.unwrap_or_default())
+///     .path("crates/my-tool")
+///     .generate()?;
+/// for file in &report.files {
+///     println!("{}", file.path);
+/// }
+/// # Ok::<(), cargo_setup::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Scaffolder {
+    mode: Mode,
+    kind: Option<Kind>,
+    preset: Option<Preset>,
+    edition: Option<Edition>,
+    license: Option<String>,
+    profile: Option<Profile>,
+    path: Option<PathBuf>,
+    template_dir: Option<PathBuf>,
+    vcs: Option<Vcs>,
+    ci: Option<CiProvider>,
+    matrix: Matrix,
+    use_cargo_new: Option<bool>,
+    include: Option<Vec<Shared>>,
+    keywords: Option<Vec<String>>,
+    badges: Option<Vec<Badge>>,
+    description: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+enum Mode {
+    New {
+        name: String,
+    },
+    Retrofit,
+    Workspace {
+        name: String,
+        members: Vec<Member>,
+    },
+    Update,
+    /// A crate's files as a new crate would get them, or a workspace root's
+    /// with `members`, for [`Mode::Update`] to bring the existing ones up
+    /// to date with. `vars` are the template variables recorded at
+    /// generation, which win over fresh ones.
+    Regenerate {
+        name: String,
+        members: Option<Vec<Member>>,
+        vars: Vars,
+    },
+}
+
+/// A crate to create inside a new workspace, in the directory `name`. Its
+/// package is called `<workspace>-<name>`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Member {
+    pub name: String,
+    pub kind: Option<Kind>,
+    pub preset: Option<Preset>,
+}
+
+impl Member {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            kind: None,
+            preset: None,
+        }
+    }
+
+    pub fn kind(mut self, kind: Kind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    pub fn preset(mut self, preset: Preset) -> Self {
+        self.preset = Some(preset);
+        self
+    }
+
+    /// The package name inside the workspace `workspace`.
+    fn package(&self, workspace: &str) -> String {
+        if self.name == workspace || self.name.starts_with(&format!("{}-", workspace)) {
+            self.name.clone()
+        } else {
+            format!("{}-{}", workspace, self.name)
+        }
+    }
+}
+
+/// `name`, `name:bin`, `name:lib` or `name:<preset>`.
+impl FromStr for Member {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, spec) = match s.split_once(':') {
+            Some((name, spec)) => (name, Some(spec)),
+            None => (s, None),
+        };
+        let member = Member::new(name);
+        match spec {
+            None => Ok(member),
+            Some("bin") => Ok(member.kind(Kind::Bin)),
+            Some("lib") => Ok(member.kind(Kind::Lib)),
+            Some(preset) => preset
+                .parse()
+                .map(|preset| member.preset(preset))
+                .map_err(|_| {
+                    format!(
+                        "unknown member kind `{}` (expected `bin`, `lib` or a preset)",
+                        preset
+                    )
+                }),
+        }
+    }
+}
+
+impl From<Member> for String {
+    fn from(member: Member) -> Self {
+        match (member.preset, member.kind) {
+            (Some(preset), _) => format!("{}:{}", member.name, preset),
+            (None, Some(kind)) => format!("{}:{}", member.name, kind.as_str()),
+            (None, None) => member.name,
+        }
+    }
+}
+
+impl TryFrom<String> for Member {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// What a scaffold run did.
+#[derive(Debug)]
+pub struct Report {
+    pub name: String,
+    /// The crate directory.
+    pub path: PathBuf,
+    /// The canonical SPDX license expression that was used.
+    pub license: String,
+    /// Files written, relative to `path`.
+    pub files: Vec<GeneratedFile>,
+    /// Pieces left alone because the crate already had them, with why.
+    pub skipped: Vec<String>,
+    /// The workspace root whose `members` the new crate was added to.
+    pub workspace: Option<PathBuf>,
+    /// Files an update wrote with conflict markers, relative to `path`.
+    pub conflicts: Vec<String>,
+}
+
+/// Files a crate inside a workspace leaves to the workspace root unless
+/// asked for with [`Scaffolder::include`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Shared {
+    Ci,
+    License,
+    Changelog,
+}
+
+impl Shared {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Shared::Ci => "ci",
+            Shared::License => "license",
+            Shared::Changelog => "changelog",
+        }
+    }
+
+    /// Which shared piece the generated file `path` belongs to, if any.
+    fn of(path: &str) -> Option<Shared> {
+        let ci = path.starts_with(".github/")
+            || path.starts_with(".forgejo/")
+            || path == "deny.toml"
+            || CiProvider::ALL
+                .iter()
+                .any(|provider| provider.path() == Some(path));
+        if ci {
+            Some(Shared::Ci)
+        } else if path.starts_with("LICENSE") {
+            Some(Shared::License)
+        } else if path.starts_with("CHANGELOG") {
+            Some(Shared::Changelog)
+        } else {
+            None
+        }
+    }
+}
+
+impl FromStr for Shared {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ci" => Ok(Shared::Ci),
+            "license" => Ok(Shared::License),
+            "changelog" => Ok(Shared::Changelog),
+            _ => Err(format!(
+                "unknown file `{}` (expected `ci`, `license` or `changelog`)",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GeneratedFile {
+    pub path: String,
+    /// Whether the file existed before and was changed.
+    pub updated: bool,
+}
+
+impl Scaffolder {
+    /// Scaffold a new crate called `name`, by default in `./<name>`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self::with_mode(Mode::New { name: name.into() })
+    }
+
+    /// Add whatever the existing crate at `path` is missing, without
+    /// replacing any file that is already there.
+    pub fn retrofit(path: impl Into<PathBuf>) -> Self {
+        Self::with_mode(Mode::Retrofit).path(path)
+    }
+
+    /// Regenerate the files `cargo setup` generated in the crate at `path`,
+    /// as listed in its `.cargo-setup.lock`, from the current templates
+    /// with the options and template variables the lock file recorded.
+    /// Options set on the scaffolder override those. Files that are still
+    /// as generated are replaced; edited ones get a three-way merge, with
+    /// conflict markers where it fails.
+    pub fn update(path: impl Into<PathBuf>) -> Self {
+        Self::with_mode(Mode::Update).path(path)
+    }
+
+    /// Scaffold a new workspace called `name`, with one shared README,
+    /// LICENSE, CHANGELOG and CI at the root and each [`Member`] in its own
+    /// directory underneath.
+    pub fn workspace(name: impl Into<String>) -> Self {
+        Self::with_mode(Mode::Workspace {
+            name: name.into(),
+            members: Vec::new(),
+        })
+    }
+
+    fn with_mode(mode: Mode) -> Self {
+        Self {
+            mode,
+            kind: None,
+            preset: None,
+            edition: None,
+            license: None,
+            profile: None,
+            path: None,
+            template_dir: None,
+            vcs: None,
+            ci: None,
+            matrix: Matrix::default(),
+            use_cargo_new: None,
+            include: None,
+            keywords: None,
+            badges: None,
+            description: None,
+        }
+    }
+
+    /// Binary or library. New crates default to the profile's `kind` or
+    /// `preset`, else a library; retrofits detect it from `src/main.rs`.
+    pub fn kind(mut self, kind: Kind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Start a new crate from a preset: its dependencies, source files,
+    /// tests and CI steps on top of the standard extras. The preset decides
+    /// whether the crate is a binary or a library. Ignored when retrofitting.
+    pub fn preset(mut self, preset: Preset) -> Self {
+        self.preset = Some(preset);
+        self
+    }
+
+    /// The edition of new crates and workspaces, overriding the profile's.
+    /// Defaults to the latest.
+    pub fn edition(mut self, edition: Edition) -> Self {
+        self.edition = Some(edition);
+        self
+    }
+
+    /// SPDX license expression, overriding the crate's and the profile's.
+    pub fn license(mut self, license: impl Into<String>) -> Self {
+        self.license = Some(license.into());
+        self
+    }
+
+    /// Author, license and GitHub defaults, and defaults for every option
+    /// left unset on the scaffolder.
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// The crate directory.
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// A directory of templates overriding or adding to the built-in ones,
+    /// instead of the profile's `template`.
+    pub fn template_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.template_dir = Some(dir.into());
+        self
+    }
+
+    /// Version control for new crates. Defaults to git, which is skipped
+    /// inside an existing git work tree like `cargo new` does.
+    pub fn vcs(mut self, vcs: Vcs) -> Self {
+        self.vcs = Some(vcs);
+        self
+    }
+
+    /// The CI provider to generate a pipeline for. Defaults to the
+    /// profile's, else GitHub Actions; it also decides where the repository
+    /// URL and README badge point.
+    pub fn ci(mut self, ci: CiProvider) -> Self {
+        self.ci = Some(ci);
+        self
+    }
+
+    /// The OSes, toolchains, MSRV and targets the pipeline covers. Settings
+    /// left unset come from the profile's `[ci]` table. An MSRV is also
+    /// written to `rust-version`.
+    pub fn ci_matrix(mut self, matrix: Matrix) -> Self {
+        self.matrix = matrix;
+        self
+    }
+
+    /// Create new crates by running `cargo new` (which must be on `PATH`)
+    /// instead of writing the project files directly. Useful to compare the
+    /// output against upstream.
+    pub fn use_cargo_new(mut self, use_cargo_new: bool) -> Self {
+        self.use_cargo_new = Some(use_cargo_new);
+        self
+    }
+
+    /// Add a crate to a new workspace. Ignored unless the scaffolder was
+    /// created with [`Scaffolder::workspace`].
+    pub fn member(mut self, member: Member) -> Self {
+        if let Mode::Workspace { members, .. } = &mut self.mode {
+            members.push(member);
+        }
+        self
+    }
+
+    /// Generate `shared` even though the new crate is inside a workspace,
+    /// which normally provides it at the root.
+    pub fn include(mut self, shared: Shared) -> Self {
+        let include = self.include.get_or_insert_with(Vec::new);
+        if !include.contains(&shared) {
+            include.push(shared);
+        }
+        self
+    }
+
+    /// Generate exactly `shared` inside a workspace, instead of what the
+    /// profile's `include` lists; an empty list generates none of them.
+    pub fn include_only(mut self, shared: Vec<Shared>) -> Self {
+        self.include = Some(shared);
+        self
+    }
+
+    /// crates.io keywords for `[package]`, instead of the profile's.
+    pub fn keywords(mut self, keywords: Vec<String>) -> Self {
+        self.keywords = Some(keywords);
+        self
+    }
+
+    /// Badges at the top of the README, instead of the profile's. Defaults
+    /// to the CI badge.
+    pub fn badges(mut self, badges: Vec<Badge>) -> Self {
+        self.badges = Some(badges);
+        self
+    }
+
+    /// `description` in `[package]`, also shown under the README's title.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Work out everything that would be written, without touching the disk.
+    pub fn plan(&self) -> Result<Plan> {
+        Ok(self.prepare()?.plan)
+    }
+
+    /// Scaffold the crate.
+    pub fn generate(&self) -> Result<Report> {
+        let prepared = self.prepare()?;
+        match &self.mode {
+            Mode::New { .. } if self.settings().use_cargo_new => {
+                Self::generate_with_cargo_new(prepared)
+            }
+            Mode::New { .. } | Mode::Workspace { .. } => Self::generate_new(prepared),
+            Mode::Retrofit | Mode::Update => Self::generate_retrofit(prepared),
+            Mode::Regenerate { .. } => unreachable!("only prepared, by `update`"),
+        }
+    }
+
+    fn root(&self) -> PathBuf {
+        match (&self.path, &self.mode) {
+            (Some(path), _) => path.clone(),
+            (
+                None,
+                Mode::New { name } | Mode::Workspace { name, .. } | Mode::Regenerate { name, .. },
+            ) => PathBuf::from(name),
+            (None, Mode::Retrofit | Mode::Update) => PathBuf::from("."),
+        }
+    }
+
+    /// Resolve the license, render every template and compute the plan.
+    /// Everything that can fail because of bad input fails here, before
+    /// anything touches the disk.
+    fn prepare(&self) -> Result<Prepared> {
+        match &self.mode {
+            Mode::New { .. } | Mode::Regenerate { members: None, .. } => {
+                self.prepare_crate(Workspace::find(&self.root())?)
+            }
+            Mode::Retrofit => self.prepare_crate(None),
+            Mode::Workspace { name, members }
+            | Mode::Regenerate {
+                name,
+                members: Some(members),
+                ..
+            } => self.prepare_workspace(name, members),
+            Mode::Update => self.prepare_update(),
+        }
+    }
+
+    /// [`prepare`](Self::prepare) for a single crate, new ones inside
+    /// `workspace` if given.
+    fn prepare_crate(&self, workspace: Option<Workspace>) -> Result<Prepared> {
+        let root = self.root();
+        let profile = self.profile.as_ref();
+        let settings = self.settings();
+
+        let (name, kind, preset, existing) = match &self.mode {
+            Mode::Workspace { .. } | Mode::Update => {
+                unreachable!("workspaces and updates are prepared separately")
+            }
+            Mode::New { name } | Mode::Regenerate { name, .. } => {
+                skeleton::validate_name(name).map_err(|reason| Error::InvalidName {
+                    name: name.clone(),
+                    reason,
+                })?;
+                if root.exists() && matches!(self.mode, Mode::New { .. }) {
+                    return Err(Error::AlreadyExists(root));
+                }
+                let (kind, preset) = self.kind_and_preset();
+                let kind = preset.map(Preset::kind).or(kind).unwrap_or_default();
+                (name.clone(), kind, preset, None)
+            }
+            Mode::Retrofit => {
+                let cargo_toml_path = root.join("Cargo.toml");
+                let cargo_toml = fs::read_to_string(&cargo_toml_path)
+                    .map_err(|err| Error::io(&cargo_toml_path, err))?;
+                let invalid_manifest = |message: String| Error::Manifest {
+                    path: cargo_toml_path.clone(),
+                    message,
+                };
+                let package = manifest::package_info(&cargo_toml)
+                    .map_err(|err| invalid_manifest(err.to_string()))?;
+                let name = package.name.clone().ok_or_else(|| {
+                    invalid_manifest(
+                        "no `[package]` name (workspace roots are not supported)".into(),
+                    )
+                })?;
+                let kind = self.kind.unwrap_or(if root.join("src/main.rs").exists() {
+                    Kind::Bin
+                } else {
+                    Kind::Lib
+                });
+                (name, kind, None, Some((cargo_toml, package)))
+            }
+        };
+
+        // A new crate in a workspace takes its license from the workspace,
+        // like an existing crate keeps its own
+        let crate_license = existing
+            .as_ref()
+            .and_then(|(_, package)| package.license.as_deref())
+            .or_else(|| workspace.as_ref()?.info.license.as_deref());
+        let license = resolve_license(self.license.as_deref(), crate_license, profile)?;
+
+        // Fields the workspace defines are inherited rather than repeated;
+        // the license only when it is the one the crate uses
+        let keys: Vec<&'static str> = match &workspace {
+            Some(workspace) => workspace
+                .inherited_keys()
+                .into_iter()
+                .filter(|&key| {
+                    key != "license"
+                        || workspace
+                            .info
+                            .license
+                            .as_deref()
+                            .and_then(|license| Expression::parse(license).ok())
+                            .is_some_and(|expr| expr.canonical == license.canonical)
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        let inherited = Inherited {
+            keys,
+            dependencies: workspace
+                .as_ref()
+                .map(|ws| ws.info.dependencies.clone())
+                .unwrap_or_default(),
+        };
+
+        // The MSRV job checks the `rust-version` the crate ends up with: its
+        // own or the workspace's, if it already has one
+        let ci = settings.ci;
+        let mut matrix = settings.matrix.clone();
+        if existing.is_none() {
+            check_new_msrv(&matrix, settings.edition, preset)?;
+        }
+        if matrix.msrv.is_some() {
+            let existing = existing
+                .as_ref()
+                .and_then(|(_, package)| package.rust_version.as_deref())
+                .or_else(|| {
+                    let workspace = workspace.as_ref()?;
+                    inherited
+                        .keys
+                        .contains(&"rust-version")
+                        .then_some(workspace.info.rust_version.as_deref()?)
+                });
+            if let Some(version) = existing.and_then(|version| version.parse().ok()) {
+                matrix.msrv = Some(version);
+            }
+        }
+
+        let mut vars = template_vars(
+            &name,
+            kind,
+            preset,
+            &settings,
+            profile,
+            &license,
+            workspace.as_ref().and_then(|ws| ws.info.repository.clone()),
+        );
+        if let Mode::Regenerate { vars: recorded, .. } = &self.mode {
+            vars.extend(recorded.clone());
+        }
+        let mut files = render_files(
+            settings.template_dir.as_deref(),
+            kind,
+            preset,
+            ci,
+            &matrix,
+            &license,
+            &vars,
+        )?;
+        let header = license_header(profile, &vars)?;
+        let metadata = package_metadata(profile, &license, &name, matrix.msrv.as_ref(), &settings)
+            .map(|mut metadata| {
+                for key in &inherited.keys {
+                    match *key {
+                        "authors" => metadata.authors = None,
+                        "license" => metadata.license = None,
+                        "repository" => metadata.repository = None,
+                        "rust-version" => metadata.rust_version = None,
+                        "description" => metadata.description = None,
+                        "keywords" => metadata.keywords = None,
+                        "publish" => metadata.publish = None,
+                        _ => {}
+                    }
+                }
+                metadata
+            });
+
+        // A regenerated crate already has what `git init` would add
+        let vcs = match self.mode {
+            Mode::Regenerate { .. } => settings.vcs,
+            _ => settings.vcs.effective(&root),
+        };
+        let retrofit = existing.is_some();
+
+        let mut planned: Vec<PlannedFile> = Vec::new();
+        let mut skipped: Vec<String> = Vec::new();
+        if kind == Kind::Bin && ci != CiProvider::None && matrix.jobs().contains(&Job::Semver) {
+            skipped.push("semver CI job (libraries only)".into());
+        }
+        let actions = matches!(ci, CiProvider::Github | CiProvider::None);
+        if kind == Kind::Bin && matrix.release() && !actions {
+            skipped.push("release workflow (GitHub Actions only)".into());
+        }
+        match existing {
+            None => {
+                // Inside a workspace, CI, LICENSE and CHANGELOG live at the root
+                let mut shared = Vec::new();
+                if workspace.is_some() {
+                    let all = std::mem::take(&mut files);
+                    (files, shared) = all.into_iter().partition(|file| {
+                        Shared::of(&file.path)
+                            .is_none_or(|shared| settings.include.contains(&shared))
+                    });
+                }
+
+                let bin = kind == Kind::Bin;
+                for (path, contents) in
+                    skeleton::files(&name, bin, settings.edition, vcs == Vcs::Git)
+                {
+                    let contents = match path.as_str() {
+                        "Cargo.toml" => {
+                            finish_manifest(&contents, preset, metadata.as_ref(), &inherited)
+                                .expect("the skeleton manifest is valid TOML")
+                        }
+                        _ => contents,
+                    };
+                    planned.push(PlannedFile {
+                        path,
+                        before: None,
+                        after: Some(contents),
+                    });
+                }
+                for file in &files {
+                    match planned.iter_mut().find(|p| p.path == file.path) {
+                        Some(existing) if file.overwrite => {
+                            existing.after = Some(file.contents.clone())
+                        }
+                        Some(_) => {}
+                        None => planned.push(PlannedFile {
+                            path: file.path.clone(),
+                            before: None,
+                            after: Some(file.contents.clone()),
+                        }),
+                    }
+                }
+                for file in shared {
+                    skipped.push(format!("{} (provided by the workspace)", file.path));
+                    planned.push(PlannedFile {
+                        path: file.path,
+                        before: None,
+                        after: None,
+                    });
+                }
+            }
+            Some((cargo_toml, _)) => {
+                let files = std::mem::take(&mut files);
+                // Metadata: only keys that aren't set yet
+                if let Some(metadata) = &metadata {
+                    let (after, kept) =
+                        manifest::fill_metadata(&cargo_toml, metadata).map_err(|err| {
+                            Error::Manifest {
+                                path: root.join("Cargo.toml"),
+                                message: err.to_string(),
+                            }
+                        })?;
+                    skipped.extend(
+                        kept.iter()
+                            .map(|key| format!("Cargo.toml `{}` (already set)", key)),
+                    );
+                    if after != cargo_toml {
+                        planned.push(PlannedFile {
+                            path: "Cargo.toml".into(),
+                            before: Some(cargo_toml),
+                            after: Some(after),
+                        });
+                    }
+                }
+
+                // Files: only pieces the crate doesn't have
+                for file in files {
+                    match apply::existing(&root, &file.path) {
+                        Some(found) => {
+                            let found = found.strip_prefix(&root).unwrap_or(&found);
+                            skipped.push(format!("{} ({} exists)", file.path, found.display()));
+                            planned.push(PlannedFile {
+                                before: fs::read_to_string(root.join(&file.path)).ok(),
+                                path: file.path,
+                                after: None,
+                            });
+                        }
+                        None => planned.push(PlannedFile {
+                            path: file.path,
+                            before: None,
+                            after: Some(file.contents),
+                        }),
+                    }
+                }
+            }
+        }
+
+        // New Rust sources start with the license header
+        if let Some(header) = &header {
+            for file in &mut planned {
+                if let (None, Some(contents)) = (&file.before, &mut file.after) {
+                    if file.path.ends_with(".rs") {
+                        contents.insert_str(0, header);
+                    }
+                }
+            }
+            for file in files.iter_mut().filter(|file| file.path.ends_with(".rs")) {
+                file.contents.insert_str(0, header);
+            }
+        }
+        let options = lock::Options {
+            kind: Some(kind),
+            preset,
+            edition: Some(settings.edition),
+            license: Some(license.canonical.clone()),
+            // A retrofit leaves version control to the crate
+            vcs: Some(if retrofit { Vcs::None } else { vcs }),
+            include: Some(settings.include.clone()),
+            description: settings.description.clone(),
+            keywords: Some(settings.keywords.clone()),
+            badges: Some(settings.badges.clone()),
+            members: None,
+            ci: Matrix {
+                provider: Some(ci),
+                ..matrix.clone()
+            },
+        };
+        let lock = plan_lock(
+            &root,
+            retrofit,
+            options,
+            vars,
+            settings.template_dir.as_deref(),
+            &mut planned,
+        )?;
+
+        // The workspace lists the new crate as a member
+        let mut outside = Vec::new();
+        let mut workspace_manifest = None;
+        if let Some(workspace) = workspace.as_ref().filter(|ws| !ws.is_member()) {
+            let manifest_path = workspace.root.join("Cargo.toml");
+            let after =
+                manifest::add_member(&workspace.manifest, &workspace.member).map_err(|err| {
+                    Error::Manifest {
+                        path: manifest_path.clone(),
+                        message: err.to_string(),
+                    }
+                })?;
+            outside.push(PlannedFile {
+                path: manifest_path.display().to_string(),
+                before: Some(workspace.manifest.clone()),
+                after: Some(after.clone()),
+            });
+            workspace_manifest = Some(after);
+        }
+
+        Ok(Prepared {
+            name,
+            kind,
+            preset,
+            edition: settings.edition,
+            vcs,
+            license: license.canonical,
+            metadata,
+            inherited,
+            workspace: workspace.map(|ws| ws.root),
+            workspace_manifest,
+            files,
+            license_header: header,
+            lock,
+            conflicts: Vec::new(),
+            plan: Plan {
+                root,
+                files: planned,
+                outside,
+            },
+            skipped,
+        })
+    }
+
+    /// [`prepare`](Self::prepare) for a new workspace: the root files, then
+    /// each member prepared like a crate inside it. Regenerating an existing
+    /// workspace prepares the root files only.
+    fn prepare_workspace(&self, name: &str, members: &[Member]) -> Result<Prepared> {
+        let root = self.root();
+        let profile = self.profile.as_ref();
+        let settings = self.settings();
+        let ci = settings.ci;
+
+        // Members without a kind or preset of their own take the profile's
+        let members: Vec<Member> = members
+            .iter()
+            .map(|member| match (member.kind, member.preset, profile) {
+                (None, None, Some(profile)) => Member {
+                    kind: profile.kind,
+                    preset: profile.preset,
+                    ..member.clone()
+                },
+                _ => member.clone(),
+            })
+            .collect();
+        let members = members.as_slice();
+
+        skeleton::validate_name(name).map_err(|reason| Error::InvalidName {
+            name: name.to_string(),
+            reason,
+        })?;
+        let regenerate = matches!(self.mode, Mode::Regenerate { .. });
+        if root.exists() && !regenerate {
+            return Err(Error::AlreadyExists(root));
+        }
+        let mut dirs: Vec<&str> = Vec::new();
+        for member in members {
+            let invalid = |reason: &str| Error::InvalidName {
+                name: member.name.clone(),
+                reason: reason.into(),
+            };
+            if member.name.is_empty() {
+                return Err(invalid("the member name cannot be empty"));
+            }
+            if dirs.contains(&member.name.as_str()) {
+                return Err(invalid("the member is listed twice"));
+            }
+            dirs.push(&member.name);
+        }
+
+        let license = resolve_license(self.license.as_deref(), None, profile)?;
+        let vcs = match regenerate {
+            true => settings.vcs,
+            false => settings.vcs.effective(&root),
+        };
+
+        // Root manifest: members, shared package fields and dependencies
+        let dependencies = workspace_dependencies(name, members);
+        let entries: Vec<_> = dependencies
+            .iter()
+            .map(|(key, value)| ("workspace.dependencies", key.as_str(), value.as_str()))
+            .collect();
+        let mut matrix = settings.matrix.clone();
+        check_new_msrv(
+            &matrix,
+            settings.edition,
+            members.iter().filter_map(|member| member.preset),
+        )?;
+        let mut manifest = skeleton::workspace_manifest(&dirs, settings.edition);
+        if let Some(metadata) =
+            package_metadata(profile, &license, name, matrix.msrv.as_ref(), &settings)
+        {
+            manifest = manifest::apply_workspace_metadata(&manifest, &metadata)
+                .expect("the skeleton manifest is valid TOML");
+        }
+        manifest = manifest::add_entries(&manifest, &entries)
+            .expect("the skeleton manifest is valid TOML");
+        let info = manifest::workspace_info(&manifest)
+            .expect("the generated manifest is valid TOML")
+            .expect("the generated manifest has a `[workspace]` table");
+        let workspace = Workspace {
+            root: root.clone(),
+            manifest: manifest.clone(),
+            member: String::new(),
+            info,
+        };
+
+        let mut planned = vec![PlannedFile {
+            path: "Cargo.toml".into(),
+            before: None,
+            after: Some(manifest),
+        }];
+        if vcs == Vcs::Git {
+            planned.push(PlannedFile {
+                path: ".gitignore".into(),
+                before: None,
+                after: Some("/target\n".into()),
+            });
+        }
+
+        // Shared README, LICENSE, CHANGELOG and CI
+        let mut vars = template_vars(name, Kind::Lib, None, &settings, profile, &license, None);
+        vars.insert("members".into(), member_list(name, members));
+        if let Mode::Regenerate { vars: recorded, .. } = &self.mode {
+            vars.extend(recorded.clone());
+        }
+        let lib = members.iter().any(|member| {
+            member
+                .preset
+                .map(Preset::kind)
+                .or(member.kind)
+                .unwrap_or_default()
+                == Kind::Lib
+        });
+
+        let options = lock::Options {
+            kind: None,
+            preset: None,
+            edition: Some(settings.edition),
+            license: Some(license.canonical.clone()),
+            vcs: Some(vcs),
+            include: None,
+            description: settings.description.clone(),
+            keywords: Some(settings.keywords.clone()),
+            badges: Some(settings.badges.clone()),
+            members: Some(members.to_vec()),
+            ci: Matrix {
+                provider: Some(ci),
+                ..matrix.clone()
+            },
+        };
+
+        // Release binaries are built per crate; a workspace only checks
+        // that its libraries would publish
+        let mut skipped = Vec::new();
+        if matrix.release() && !lib {
+            matrix.release = Some(false);
+            skipped.push("release workflow (single crates only)".to_string());
+        }
+        for file in render_workspace_files(
+            settings.template_dir.as_deref(),
+            ci,
+            &matrix,
+            lib,
+            &license,
+            &vars,
+        )? {
+            planned.push(PlannedFile {
+                path: file.path,
+                before: None,
+                after: Some(file.contents),
+            });
+        }
+        let lock = plan_lock(
+            &root,
+            false,
+            options,
+            vars,
+            settings.template_dir.as_deref(),
+            &mut planned,
+        )?;
+
+        // Each member, inheriting from the workspace
+        for member in members.iter().filter(|_| !regenerate) {
+            let scaffolder = Scaffolder {
+                mode: Mode::New {
+                    name: member.package(name),
+                },
+                kind: member.kind,
+                preset: member.preset,
+                edition: Some(settings.edition),
+                license: Some(license.canonical.clone()),
+                profile: self.profile.clone(),
+                path: Some(root.join(&member.name)),
+                template_dir: settings.template_dir.clone(),
+                vcs: Some(Vcs::None),
+                ci: Some(ci),
+                matrix: self.matrix.clone(),
+                use_cargo_new: Some(false),
+                include: Some(Vec::new()),
+                keywords: Some(settings.keywords.clone()),
+                badges: Some(settings.badges.clone()),
+                description: None,
+            };
+            let prepared = scaffolder.prepare_crate(Some(Workspace {
+                member: member.name.clone(),
+                ..workspace.clone()
+            }))?;
+            for file in prepared.plan.files {
+                if file.after.is_some() {
+                    planned.push(PlannedFile {
+                        path: format!("{}/{}", member.name, file.path),
+                        before: None,
+                        after: file.after,
+                    });
+                }
+            }
+        }
+
+        Ok(Prepared {
+            name: name.to_string(),
+            kind: Kind::Lib,
+            preset: None,
+            edition: settings.edition,
+            vcs,
+            license: license.canonical,
+            metadata: None,
+            inherited: Inherited::default(),
+            workspace: None,
+            workspace_manifest: None,
+            files: Vec::new(),
+            license_header: None,
+            lock,
+            conflicts: Vec::new(),
+            plan: Plan {
+                root,
+                files: planned,
+                outside: Vec::new(),
+            },
+            skipped,
+        })
+    }
+
+    /// The scaffolder for generating the files of the crate at `self.path`
+    /// again, as they were generated when its `lock` was written: the same
+    /// options, template directory and template variables, except where
+    /// `self` sets its own. The profile only fills in what the lock file
+    /// doesn't record.
+    fn regenerate(&self, lock: &Lock, name: &str) -> Scaffolder {
+        let options = &lock.options;
+        let var = |key: &str| lock.vars.get(key).filter(|value| !value.is_empty());
+
+        // Lock files without `[options]` still have the variables
+        let (kind, preset) = match (self.kind, self.preset) {
+            (None, None) => (
+                options.kind.or_else(|| var("kind")?.parse().ok()),
+                options.preset.or_else(|| var("preset")?.parse().ok()),
+            ),
+            (kind, preset) => (kind, preset),
+        };
+        let include = options.include.clone().unwrap_or_else(|| {
+            let mut include: Vec<Shared> = Vec::new();
+            for shared in lock.files.iter().filter_map(|file| Shared::of(&file.path)) {
+                if !include.contains(&shared) {
+                    include.push(shared);
+                }
+            }
+            include
+        });
+        let vcs = options.vcs.unwrap_or(match lock.file(".gitignore") {
+            Some(_) => Vcs::Git,
+            None => Vcs::None,
+        });
+        let template_dir = self.template_dir.clone().or_else(|| {
+            let dir = lock.template_dir.as_ref()?;
+            Some(PathBuf::from(&dir.path))
+        });
+
+        // The identity the files were rendered with, for `[package]` too,
+        // and no template directory but the recorded one
+        let profile = self.profile.clone().map(|mut profile| {
+            for (field, key) in [
+                (&mut profile.name, "author"),
+                (&mut profile.email, "email"),
+                (&mut profile.github, "github"),
+                (&mut profile.organization, "organization"),
+            ] {
+                if lock.vars.contains_key(key) {
+                    *field = var(key).cloned();
+                }
+            }
+            profile.template = None;
+            profile
+        });
+
+        // The variables that options set here decide are rendered afresh
+        let mut fresh: Vec<&str> = Vec::new();
+        if self.kind.is_some() || self.preset.is_some() {
+            fresh.extend(["kind", "preset"]);
+        }
+        if self.edition.is_some() {
+            fresh.push("edition");
+        }
+        if self.license.is_some() {
+            fresh.extend(["license", "badges"]);
+        }
+        if self.ci.is_some() || self.matrix.provider.is_some() {
+            fresh.extend(["ci", "ci_badge", "repository", "badges"]);
+        }
+        if self.badges.is_some() {
+            fresh.push("badges");
+        }
+        if self.description.is_some() {
+            fresh.push("description");
+        }
+        let vars = lock
+            .vars
+            .iter()
+            .filter(|(key, _)| {
+                let preset_var = key.starts_with("preset_") && fresh.contains(&"preset");
+                !preset_var && !fresh.contains(&key.as_str())
+            })
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        Scaffolder {
+            mode: Mode::Regenerate {
+                name: name.to_string(),
+                members: options.members.clone(),
+                vars,
+            },
+            kind,
+            preset,
+            edition: self
+                .edition
+                .or(options.edition)
+                .or_else(|| var("edition")?.parse().ok()),
+            license: self
+                .license
+                .clone()
+                .or_else(|| options.license.clone())
+                .or_else(|| var("license").cloned()),
+            profile,
+            path: self.path.clone(),
+            template_dir,
+            vcs: Some(vcs),
+            ci: self
+                .ci
+                .or(self.matrix.provider)
+                .or(options.ci.provider)
+                .or_else(|| var("ci")?.parse().ok()),
+            matrix: self.matrix.clone().or(&options.ci),
+            use_cargo_new: Some(false),
+            include: Some(include),
+            keywords: self.keywords.clone().or_else(|| options.keywords.clone()),
+            badges: self.badges.clone().or_else(|| options.badges.clone()),
+            description: self
+                .description
+                .clone()
+                .or_else(|| options.description.clone())
+                .or_else(|| var("description").cloned()),
+        }
+    }
+
+    /// [`prepare`](Self::prepare) for an update: the crate regenerated with
+    /// the choices its lock file recorded (unless set on the scaffolder),
+    /// then each file brought up to date from it.
+    fn prepare_update(&self) -> Result<Prepared> {
+        let root = self.root();
+        let invalid = |message: &str| Error::Lock {
+            path: root.join(LOCK_FILE),
+            message: message.to_string(),
+        };
+        let lock = Lock::read(&root)?.ok_or_else(|| {
+            invalid("not found; only crates scaffolded by cargo-setup can be updated")
+        })?;
+        let workspace_root = lock.vars.contains_key("members");
+        if workspace_root && lock.options.members.is_none() {
+            return Err(invalid(
+                "no `members` in `[options]` for this workspace root",
+            ));
+        }
+        let var = |key: &str| lock.vars.get(key).filter(|value| !value.is_empty());
+        let name = var("crate_name").ok_or_else(|| invalid("no `crate_name` in `[vars]`"))?;
+        let fresh = self.regenerate(&lock, name).prepare()?;
+        let mut new_lock = fresh.lock.expect("a regenerated crate always writes files");
+        new_lock.files = lock.files.clone();
+
+        let fresh_paths: Vec<String> = fresh
+            .plan
+            .files
+            .iter()
+            .filter(|file| file.after.is_some())
+            .map(|file| file.path.clone())
+            .collect();
+        let mut planned = Vec::new();
+        let mut skipped = fresh.skipped;
+        let mut conflicts = Vec::new();
+        for file in fresh.plan.files {
+            let Some(mut new) = file.after.filter(|_| file.path != LOCK_FILE) else {
+                continue;
+            };
+            let path = root.join(&file.path);
+            let current = match fs::read_to_string(&path) {
+                Ok(current) => Some(current),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+                Err(err) => return Err(Error::io(&path, err)),
+            };
+            let generated = lock.file(&file.path);
+            let base = generated.and_then(|generated| generated.contents.clone());
+            // A crate's manifest keeps everything but the metadata as it
+            // was generated: for a retrofit, that is the crate's own
+            // manifest. A workspace root's is merged like any other file.
+            if file.path == "Cargo.toml" && !workspace_root {
+                let base = base.as_deref().or(current.as_deref()).unwrap_or_default();
+                new = match &fresh.metadata {
+                    Some(metadata) => {
+                        manifest::apply_metadata(base, metadata).map_err(|err| Error::Manifest {
+                            path: path.clone(),
+                            message: err.to_string(),
+                        })?
+                    }
+                    None => base.to_string(),
+                };
+            }
+
+            let after = match (generated, current.as_deref()) {
+                (Some(_), None) => {
+                    skipped.push(format!("{} (deleted since it was generated)", file.path));
+                    continue;
+                }
+                (None, _) => match apply::existing(&root, &file.path) {
+                    Some(found) => {
+                        let found = found.strip_prefix(&root).unwrap_or(&found);
+                        skipped.push(format!("{} ({} exists)", file.path, found.display()));
+                        continue;
+                    }
+                    None => new.clone(),
+                },
+                (Some(generated), Some(current)) => {
+                    if generated.hash == lock::hash(current.as_bytes()) {
+                        new.clone()
+                    } else {
+                        // Without the generated contents, every difference
+                        // is a conflict
+                        let base = base.as_deref().unwrap_or_default();
+                        let merged = merge::merge(base, current, &new);
+                        if merged.conflicts > 0 {
+                            conflicts.push(file.path.clone());
+                        }
+                        merged.text
+                    }
+                }
+            };
+            new_lock.record(&file.path, &new);
+            if current.as_ref() != Some(&after) {
+                planned.push(PlannedFile {
+                    path: file.path,
+                    before: current,
+                    after: Some(after),
+                });
+            }
+        }
+        for file in lock
+            .files
+            .iter()
+            .filter(|file| !fresh_paths.contains(&file.path))
+        {
+            skipped.push(format!("{} (no longer generated)", file.path));
+        }
+        let before = fs::read_to_string(root.join(LOCK_FILE)).ok();
+        let after = new_lock.to_toml();
+        if before.as_ref() != Some(&after) {
+            planned.push(PlannedFile {
+                path: LOCK_FILE.into(),
+                before,
+                after: Some(after),
+            });
+        }
+
+        Ok(Prepared {
+            name: name.clone(),
+            kind: fresh.kind,
+            preset: fresh.preset,
+            edition: fresh.edition,
+            vcs: Vcs::None,
+            license: fresh.license,
+            metadata: None,
+            inherited: Inherited::default(),
+            workspace: None,
+            workspace_manifest: None,
+            files: Vec::new(),
+            license_header: None,
+            lock: None,
+            conflicts,
+            plan: Plan {
+                root,
+                files: planned,
+                outside: Vec::new(),
+            },
+            skipped,
+        })
+    }
+
+    /// The scaffolder's options, with the ones left unset taken from the
+    /// profile and then the defaults.
+    fn settings(&self) -> Settings {
+        let default = Profile::default();
+        let profile = self.profile.as_ref().unwrap_or(&default);
+        let matrix = self.matrix.clone().or(&profile.ci);
+        Settings {
+            edition: self.edition.or(profile.edition).unwrap_or_default(),
+            vcs: self.vcs.or(profile.vcs).unwrap_or_default(),
+            ci: self.ci.or(matrix.provider).unwrap_or_default(),
+            matrix,
+            use_cargo_new: self
+                .use_cargo_new
+                .or(profile.use_cargo_new)
+                .unwrap_or(false),
+            include: self
+                .include
+                .clone()
+                .or_else(|| profile.include.clone())
+                .unwrap_or_default(),
+            keywords: self
+                .keywords
+                .clone()
+                .or_else(|| profile.keywords.clone())
+                .unwrap_or_default(),
+            badges: self
+                .badges
+                .clone()
+                .or_else(|| profile.badges.clone())
+                .unwrap_or_else(|| vec![Badge::Ci]),
+            template_dir: self.template_dir.clone().or_else(|| {
+                let dir = profile.template.as_deref()?;
+                Some(profile::expand_home(dir).unwrap_or_else(|| PathBuf::from(dir)))
+            }),
+            publish: profile.publish,
+            description: self.description.clone(),
+        }
+    }
+
+    /// The kind and preset of a new crate: the scaffolder's if either is
+    /// set, else the profile's.
+    fn kind_and_preset(&self) -> (Option<Kind>, Option<Preset>) {
+        match (self.kind, self.preset, &self.profile) {
+            (None, None, Some(profile)) => (profile.kind, profile.preset),
+            _ => (self.kind, self.preset),
+        }
+    }
+
+    fn generate_new(prepared: Prepared) -> Result<Report> {
+        let crate_path = prepared.plan.root;
+
+        // Generate everything in a staging directory next to the target; it
+        // is deleted on any failure and only renamed into place at the end
+        let staging =
+            transaction::staging_dir(&crate_path).map_err(|err| Error::io(&crate_path, err))?;
+        let staged = staging.path().join(&prepared.name);
+
+        // 0. List the crate in the workspace; undone unless the crate is
+        // created too
+        let workspace = prepared
+            .workspace
+            .as_deref()
+            .zip(prepared.workspace_manifest.as_deref())
+            .map(|(root, manifest)| join_workspace(root, manifest))
+            .transpose()?;
+
+        // 1. Write Cargo.toml, src/, .gitignore and the rendered extras
+        let mut files = Vec::new();
+        for file in prepared.plan.files {
+            let Some(contents) = file.after else {
+                continue;
+            };
+            let path = staged.join(&file.path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|err| Error::io(parent, err))?;
+            }
+            fs::write(&path, contents).map_err(|err| Error::io(&path, err))?;
+            files.push(GeneratedFile {
+                path: file.path,
+                updated: false,
+            });
+        }
+
+        // 2. Initialize version control
+        if prepared.vcs == Vcs::Git {
+            vcs::init_git(&staged)?;
+        }
+
+        // 3. Move the finished crate into place
+        transaction::commit_dir(&staged, &crate_path).map_err(|err| Error::io(&crate_path, err))?;
+        if let Some(workspace) = workspace {
+            workspace.commit();
+        }
+
+        Ok(Report {
+            name: prepared.name,
+            path: crate_path,
+            license: prepared.license,
+            files,
+            skipped: prepared.skipped,
+            workspace: prepared.workspace,
+            conflicts: Vec::new(),
+        })
+    }
+
+    /// The `--use-cargo-new` path: let `cargo new` create the project, then
+    /// add the metadata and rendered files on top.
+    fn generate_with_cargo_new(prepared: Prepared) -> Result<Report> {
+        let Prepared {
+            name,
+            kind,
+            preset,
+            edition,
+            vcs,
+            license,
+            metadata,
+            inherited,
+            workspace: workspace_root,
+            workspace_manifest,
+            files: rendered,
+            license_header,
+            lock,
+            plan,
+            skipped,
+            ..
+        } = prepared;
+        let crate_path = plan.root;
+        let files: Vec<GeneratedFile> = plan
+            .files
+            .into_iter()
+            .filter(|file| file.after.is_some())
+            .map(|file| GeneratedFile {
+                path: file.path,
+                updated: false,
+            })
+            .collect();
+
+        // Generate everything in a staging directory next to the target; it
+        // is deleted on any failure and only renamed into place at the end
+        let staging =
+            transaction::staging_dir(&crate_path).map_err(|err| Error::io(&crate_path, err))?;
+        let staged = staging.path().join(&name);
+
+        // 0. List the crate in the workspace; undone unless the crate is
+        // created too
+        let workspace = workspace_root
+            .as_deref()
+            .zip(workspace_manifest.as_deref())
+            .map(|(root, manifest)| join_workspace(root, manifest))
+            .transpose()?;
+
+        // 1. Run cargo new
+        let mut cmd = Command::new("cargo");
+        cmd.arg("new").arg("--name").arg(&name).arg(&staged);
+        cmd.arg(if kind == Kind::Bin { "--bin" } else { "--lib" });
+        cmd.arg("--edition").arg(edition.as_str());
+        cmd.arg("--vcs").arg(vcs.as_str());
+        let command = format!("cargo new {}", name);
+        let status = cmd.status().map_err(|source| Error::Spawn {
+            command: command.clone(),
+            source,
+        })?;
+        if !status.success() {
+            return Err(Error::CommandFailed { command, status });
+        }
+        // cargo new lists the staging path as a member; put ours back
+        if let (Some(root), Some(manifest)) = (&workspace_root, &workspace_manifest) {
+            fs::write(root.join("Cargo.toml"), manifest)
+                .map_err(|err| Error::io(root.join("Cargo.toml"), err))?;
+        }
+
+        // 2. Enhance Cargo.toml
+        let cargo_toml_path = staged.join("Cargo.toml");
+        let cargo_toml =
+            fs::read_to_string(&cargo_toml_path).map_err(|err| Error::io(&cargo_toml_path, err))?;
+        let cargo_toml = finish_manifest(&cargo_toml, preset, metadata.as_ref(), &inherited)
+            .map_err(|err| Error::Manifest {
+                path: cargo_toml_path.clone(),
+                message: err.to_string(),
+            })?;
+        fs::write(&cargo_toml_path, cargo_toml).map_err(|err| Error::io(&cargo_toml_path, err))?;
+
+        // The sources cargo new wrote get the license header too
+        if let Some(header) = &license_header {
+            for source in ["src/main.rs", "src/lib.rs"] {
+                let path = staged.join(source);
+                if let Ok(contents) = fs::read_to_string(&path) {
+                    fs::write(&path, format!("{}{}", header, contents))
+                        .map_err(|err| Error::io(&path, err))?;
+                }
+            }
+        }
+
+        // 3. Write the rendered files into the new crate
+        for file in rendered {
+            let path = staged.join(&file.path);
+            if path.exists() && !file.overwrite {
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|err| Error::io(parent, err))?;
+            }
+            fs::write(&path, file.contents).map_err(|err| Error::io(&path, err))?;
+        }
+
+        // 4. Record what cargo new and the templates produced
+        if let Some(mut lock) = lock {
+            for file in files.iter().filter(|file| file.path != LOCK_FILE) {
+                let path = staged.join(&file.path);
+                let contents = fs::read_to_string(&path).map_err(|err| Error::io(&path, err))?;
+                lock.record(&file.path, &contents);
+            }
+            let path = staged.join(LOCK_FILE);
+            fs::write(&path, lock.to_toml()).map_err(|err| Error::io(&path, err))?;
+        }
+
+        // 5. Move the finished crate into place
+        transaction::commit_dir(&staged, &crate_path).map_err(|err| Error::io(&crate_path, err))?;
+        if let Some(workspace) = workspace {
+            workspace.commit();
+        }
+
+        Ok(Report {
+            name,
+            path: crate_path,
+            license,
+            files,
+            skipped,
+            workspace: workspace_root,
+            conflicts: Vec::new(),
+        })
+    }
+
+    fn generate_retrofit(prepared: Prepared) -> Result<Report> {
+        let crate_path = prepared.plan.root;
+
+        // Any failure before `commit` restores the crate to how it was
+        let mut transaction = Transaction::new(&crate_path);
+        let mut files = Vec::new();
+        for file in prepared.plan.files {
+            let Some(contents) = file.after else {
+                continue;
+            };
+            transaction
+                .write(&file.path, contents.as_bytes())
+                .map_err(|err| Error::io(crate_path.join(&file.path), err))?;
+            files.push(GeneratedFile {
+                path: file.path,
+                updated: file.before.is_some(),
+            });
+        }
+        transaction.commit();
+
+        Ok(Report {
+            name: prepared.name,
+            path: crate_path,
+            license: prepared.license,
+            files,
+            skipped: prepared.skipped,
+            workspace: None,
+            conflicts: prepared.conflicts,
+        })
+    }
+}
+
+/// Write the workspace manifest listing the new crate, in a transaction the
+/// caller commits once the crate exists.
+fn join_workspace(root: &Path, manifest: &str) -> Result<Transaction> {
+    let mut transaction = Transaction::new(root);
+    transaction
+        .write("Cargo.toml", manifest.as_bytes())
+        .map_err(|err| Error::io(root.join("Cargo.toml"), err))?;
+    Ok(transaction)
+}
+
+/// [`Scaffolder`] options after [`Scaffolder::settings`] filled them in.
+struct Settings {
+    edition: Edition,
+    vcs: Vcs,
+    ci: CiProvider,
+    matrix: Matrix,
+    use_cargo_new: bool,
+    include: Vec<Shared>,
+    keywords: Vec<String>,
+    badges: Vec<Badge>,
+    template_dir: Option<PathBuf>,
+    publish: Option<bool>,
+    description: Option<String>,
+}
+
+struct Prepared {
+    name: String,
+    kind: Kind,
+    preset: Option<Preset>,
+    edition: Edition,
+    /// Version control to initialize for new crates.
+    vcs: Vcs,
+    license: String,
+    metadata: Option<Metadata>,
+    inherited: Inherited,
+    /// The root of the workspace a new crate belongs to.
+    workspace: Option<PathBuf>,
+    /// The workspace's Cargo.toml with the crate added to `members`, if it
+    /// isn't listed yet.
+    workspace_manifest: Option<String>,
+    /// Rendered templates, for new crates. Retrofits work from `plan`.
+    files: Vec<RenderedFile>,
+    /// The rendered license header new `.rs` files start with.
+    license_header: Option<String>,
+    /// The lock file in `plan`, for `cargo new` to fill in from what it
+    /// actually wrote.
+    lock: Option<Lock>,
+    /// Files an update merges with conflict markers.
+    conflicts: Vec<String>,
+    plan: Plan,
+    skipped: Vec<String>,
+}
+
+/// Add the lock file to `planned`, listing the files it writes; a retrofit
+/// adds them to the crate's lock file, if it has one. `None` when nothing
+/// is written.
+fn plan_lock(
+    root: &Path,
+    retrofit: bool,
+    options: lock::Options,
+    vars: Vars,
+    template_dir: Option<&Path>,
+    planned: &mut Vec<PlannedFile>,
+) -> Result<Option<Lock>> {
+    if planned.iter().all(|file| file.after.is_none()) {
+        return Ok(None);
+    }
+    let mut lock = Lock::new(options, vars, template_dir)?;
+    let mut before = None;
+    if retrofit {
+        if let Some(existing) = Lock::read(root)? {
+            lock.files = existing.files;
+            before = fs::read_to_string(root.join(LOCK_FILE)).ok();
+        }
+    }
+    for file in planned.iter() {
+        if let Some(contents) = &file.after {
+            lock.record(&file.path, contents);
+        }
+    }
+    planned.push(PlannedFile {
+        path: LOCK_FILE.into(),
+        before,
+        after: Some(lock.to_toml()),
+    });
+    Ok(Some(lock))
+}
+
+/// The license to use: the explicit one, then the crate's own (when
+/// retrofitting), then the profile's, then MIT. It must be a valid SPDX
+/// expression.
+fn resolve_license(
+    explicit: Option<&str>,
+    existing: Option<&str>,
+    profile: Option<&Profile>,
+) -> Result<Expression> {
+    let from_profile = explicit.is_none() && existing.is_none();
+    let license = explicit
+        .or(existing)
+        .or_else(|| profile.and_then(|p| p.license.as_deref()))
+        .unwrap_or("MIT");
+    Expression::parse(license).map_err(|source| Error::License {
+        source,
+        origin: match profile {
+            Some(profile) if from_profile => match profile.origins.get("license") {
+                Some(Origin::File { path, .. }) => Some(path.clone()),
+                Some(_) => None,
+                None => profile.source.clone(),
+            },
+            _ => None,
+        },
+    })
+}
+
+/// Compilers older than the edition of a new crate, or than the code its
+/// presets generate, can't build it.
+fn check_new_msrv(
+    matrix: &Matrix,
+    edition: Edition,
+    presets: impl IntoIterator<Item = Preset>,
+) -> Result<()> {
+    let Some(msrv) = &matrix.msrv else {
+        return Ok(());
+    };
+    if msrv.is_older_than(edition.rust_version()) {
+        return Err(Error::Msrv {
+            version: msrv.to_string(),
+            reason: format!(
+                "edition {} needs Rust {} or later",
+                edition,
+                edition.rust_version()
+            ),
+        });
+    }
+    for preset in presets {
+        match preset.rust_version() {
+            Some(version) if msrv.is_older_than(version) => {
+                return Err(Error::Msrv {
+                    version: msrv.to_string(),
+                    reason: format!("the {} preset needs Rust {} or later", preset, version),
+                })
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Render the built-in templates, the CI pipeline, the license files, the
+/// preset's files and the user's template directory.
+fn render_files(
+    template_dir: Option<&Path>,
+    kind: Kind,
+    preset: Option<Preset>,
+    ci: CiProvider,
+    matrix: &Matrix,
+    license: &Expression,
+    vars: &Vars,
+) -> Result<Vec<RenderedFile>> {
+    let mut builtin = template::builtin();
+    let steps = preset.map(Preset::ci_steps).unwrap_or_default();
+    builtin.extend(ci::templates(
+        ci,
+        matrix,
+        &steps,
+        kind == Kind::Lib,
+        license,
+    ));
+    for (file_name, text) in license.files() {
+        builtin.push(Template::new(file_name, text));
+    }
+
+    // Preset files replace the skeleton's; the user's templates win over both
+    let mut user = preset.map(Preset::templates).unwrap_or_default();
+    if let Some(dir) = template_dir {
+        user.extend(template::load_dir(dir).map_err(|err| Error::io(dir, err))?);
+    }
+
+    template::render_all(&builtin, &user, vars).map_err(|source| Error::Template {
+        path: template_dir.map(Path::to_path_buf),
+        source,
+    })
+}
+
+/// Render the files at the root of a new workspace: its README, the license
+/// files, CHANGELOG and CI, and the user's templates for those. `lib` says
+/// whether any member is a library.
+fn render_workspace_files(
+    template_dir: Option<&Path>,
+    ci: CiProvider,
+    matrix: &Matrix,
+    lib: bool,
+    license: &Expression,
+    vars: &Vars,
+) -> Result<Vec<RenderedFile>> {
+    let mut builtin = template::builtin_workspace();
+    builtin.extend(ci::templates(ci, matrix, &[], lib, license));
+    for (file_name, text) in license.files() {
+        builtin.push(Template::new(file_name, text));
+    }
+
+    let mut user = Vec::new();
+    if let Some(dir) = template_dir {
+        user = template::load_dir(dir).map_err(|err| Error::io(dir, err))?;
+        user.retain(|template| {
+            template.path == "README.md" || Shared::of(&template.path).is_some()
+        });
+    }
+
+    template::render_all(&builtin, &user, vars).map_err(|source| Error::Template {
+        path: template_dir.map(Path::to_path_buf),
+        source,
+    })
+}
+
+/// What a crate inside a workspace takes from it.
+#[derive(Default)]
+struct Inherited {
+    /// `[package]` keys written as `key.workspace = true`.
+    keys: Vec<&'static str>,
+    /// `[workspace.dependencies]` keys, depended on with
+    /// `{ workspace = true }`.
+    dependencies: Vec<String>,
+}
+
+/// A new crate's Cargo.toml: the preset's entries, the profile metadata,
+/// then the keys inherited from the workspace.
+fn finish_manifest(
+    contents: &str,
+    preset: Option<Preset>,
+    metadata: Option<&Metadata>,
+    inherited: &Inherited,
+) -> Result<String, manifest::ManifestError> {
+    let mut contents = contents.to_string();
+    if let Some(preset) = preset {
+        let entries: Vec<_> = preset
+            .manifest_entries()
+            .iter()
+            .map(|&(table, key, value)| {
+                let from_workspace =
+                    table == "dependencies" && inherited.dependencies.iter().any(|dep| dep == key);
+                if from_workspace {
+                    (table, key, "{ workspace = true }")
+                } else {
+                    (table, key, value)
+                }
+            })
+            .collect();
+        contents = manifest::add_entries(&contents, &entries)?;
+    }
+    if let Some(metadata) = metadata {
+        contents = manifest::apply_metadata(&contents, metadata)?;
+    }
+    if !inherited.keys.is_empty() {
+        contents = manifest::inherit_keys(&contents, &inherited.keys)?;
+    }
+    Ok(contents)
+}
+
+/// `[workspace.dependencies]` of a new workspace as `(key, value)`: every
+/// library member by path, then the dependencies of the members' presets.
+fn workspace_dependencies(workspace: &str, members: &[Member]) -> Vec<(String, String)> {
+    let mut dependencies: Vec<(String, String)> = Vec::new();
+    for member in members {
+        let kind = member.preset.map(Preset::kind).or(member.kind);
+        if kind.unwrap_or_default() == Kind::Lib {
+            dependencies.push((
+                member.package(workspace),
+                format!("{{ version = \"0.1.0\", path = \"{}\" }}", member.name),
+            ));
+        }
+    }
+    for preset in members.iter().filter_map(|member| member.preset) {
+        for &(table, key, value) in preset.manifest_entries() {
+            if table == "dependencies" && !dependencies.iter().any(|(dep, _)| dep == key) {
+                dependencies.push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+    dependencies
+}
+
+/// The `members` template variable: a Markdown list of the workspace's
+/// crates.
+fn member_list(workspace: &str, members: &[Member]) -> String {
+    members
+        .iter()
+        .map(|member| {
+            let kind = match (member.preset, member.kind) {
+                (Some(preset), _) => preset.as_str(),
+                (None, kind) => kind.unwrap_or_default().as_str(),
+            };
+            format!(
+                "- [`{}`]({}) ({})",
+                member.package(workspace),
+                member.name,
+                kind
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `[package]` metadata filled in from the profile, plus the MSRV and the
+/// description, keywords and `publish` from `settings`. `None` when there is
+/// none of them.
+fn package_metadata(
+    profile: Option<&Profile>,
+    license: &Expression,
+    name: &str,
+    msrv: Option<&RustVersion>,
+    settings: &Settings,
+) -> Option<Metadata> {
+    let rust_version = msrv.map(|msrv| msrv.to_string());
+    let keywords = (!settings.keywords.is_empty()).then(|| settings.keywords.clone());
+    let description = settings.description.clone();
+    let publish = settings.publish;
+    match profile {
+        Some(profile) => Some(Metadata {
+            authors: profile.author(),
+            description,
+            license: Some(license.canonical.clone()),
+            repository: repository_base(profile, settings.ci)
+                .map(|base| format!("{}/{}", base, name)),
+            rust_version,
+            keywords,
+            publish,
+        }),
+        None if rust_version.is_some()
+            || description.is_some()
+            || keywords.is_some()
+            || publish.is_some() =>
+        {
+            Some(Metadata {
+                description,
+                rust_version,
+                keywords,
+                publish,
+                ..Metadata::default()
+            })
+        }
+        None => None,
+    }
+}
+
+/// Where the profile's repositories live: GitHub for GitHub Actions (or no
+/// CI); otherwise the profile's `forge`, or the GitHub handle on the CI
+/// provider's default host.
+fn repository_base(profile: &Profile, ci: CiProvider) -> Option<String> {
+    let forge = match ci {
+        CiProvider::Github | CiProvider::None => None,
+        _ => profile.forge.as_deref(),
+    };
+    match forge {
+        Some(forge) => Some(forge.trim_end_matches('/').to_string()),
+        None => profile
+            .github
+            .as_ref()
+            .map(|gh| format!("{}/{}", ci.default_host(), gh)),
+    }
+}
+
+/// The profile's `license-header`, rendered with `vars` and followed by a
+/// blank line.
+fn license_header(profile: Option<&Profile>, vars: &Vars) -> Result<Option<String>> {
+    let Some(profile) = profile else {
+        return Ok(None);
+    };
+    let Some(header) = profile.license_header.as_deref() else {
+        return Ok(None);
+    };
+    let header = template::render(header, vars).map_err(|err| Error::Template {
+        path: match profile.origins.get("license-header") {
+            Some(Origin::File { path, .. }) => Some(path.clone()),
+            _ => None,
+        },
+        source: TemplateError::InFile("license-header".into(), Box::new(err)),
+    })?;
+    Ok(Some(format!("{}\n\n", header.trim_end())))
+}
+
+/// Variables available to templates, from the profile and the options.
+/// `repository` overrides the URL derived from the profile, e.g. with the
+/// workspace's.
+fn template_vars(
+    name: &str,
+    kind: Kind,
+    preset: Option<Preset>,
+    settings: &Settings,
+    profile: Option<&Profile>,
+    license: &Expression,
+    repository: Option<String>,
+) -> Vars {
+    let ci = settings.ci;
+    let field = |get: fn(&Profile) -> &Option<String>| {
+        profile.and_then(|p| get(p).clone()).unwrap_or_default()
+    };
+    let author = field(|p| &p.name);
+    let email = field(|p| &p.email);
+    let github = field(|p| &p.github);
+    let organization = field(|p| &p.organization);
+
+    let copyright_holder = [&organization, &author]
+        .into_iter()
+        .find(|value| !value.is_empty())
+        .cloned()
+        .unwrap_or_else(|| "Your Org".into());
+    let github_owner = if github.is_empty() {
+        "your-github".to_string()
+    } else {
+        github.clone()
+    };
+    let repository = repository
+        .or_else(|| {
+            profile
+                .and_then(|p| repository_base(p, ci))
+                .map(|base| format!("{}/{}", base, name))
+        })
+        .unwrap_or_else(|| format!("{}/{}/{}", ci.default_host(), github_owner, name));
+    let woodpecker = profile
+        .and_then(|p| p.woodpecker.clone())
+        .unwrap_or_else(|| "https://ci.codeberg.org".into());
+
+    let mut vars = Vars::new();
+    vars.insert("crate_name".into(), name.to_string());
+    vars.insert("crate_ident".into(), name.replace('-', "_"));
+    vars.insert(
+        "description".into(),
+        settings.description.clone().unwrap_or_default(),
+    );
+    vars.insert("kind".into(), kind.as_str().into());
+    vars.insert("edition".into(), settings.edition.as_str().into());
+    vars.insert(
+        "preset".into(),
+        preset.map(Preset::as_str).unwrap_or_default().into(),
+    );
+    if let Some(preset) = preset {
+        vars.insert(preset.var(), "true".into());
+    }
+    vars.insert("author".into(), author);
+    vars.insert("email".into(), email);
+    vars.insert("github".into(), github);
+    vars.insert("ci".into(), ci.as_str().into());
+    let ci_badge = ci.badge(&repository, woodpecker.trim_end_matches('/'));
+    let badges: Vec<String> = settings
+        .badges
+        .iter()
+        .filter_map(|badge| badge.markdown(name, &license.canonical, ci_badge.as_deref()))
+        .collect();
+    vars.insert("badges".into(), badges.join("\n"));
+    vars.insert("ci_badge".into(), ci_badge.unwrap_or_default());
+    vars.insert("repository".into(), repository);
+    vars.insert("github_owner".into(), github_owner);
+    vars.insert("organization".into(), organization);
+    vars.insert("copyright_holder".into(), copyright_holder);
+    vars.insert("license".into(), license.canonical.clone());
+    vars.insert("year".into(), chrono::Utc::now().year().to_string());
+    vars
+}
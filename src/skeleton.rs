@@ -0,0 +1,172 @@
+//! The project files `cargo new` would create, generated natively.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// The Rust edition of a new crate. Defaults to the latest, like `cargo
+/// new`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Edition {
+    #[serde(rename = "2015")]
+    E2015,
+    #[serde(rename = "2018")]
+    E2018,
+    #[serde(rename = "2021")]
+    E2021,
+    #[default]
+    #[serde(rename = "2024")]
+    E2024,
+}
+
+impl Edition {
+    pub const ALL: [Edition; 4] = [
+        Edition::E2015,
+        Edition::E2018,
+        Edition::E2021,
+        Edition::E2024,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        }
+    }
+
+    /// The first Rust release supporting the edition.
+    pub fn rust_version(self) -> &'static str {
+        match self {
+            Edition::E2015 => "1.0",
+            Edition::E2018 => "1.31",
+            Edition::E2021 => "1.56",
+            Edition::E2024 => "1.85",
+        }
+    }
+
+    /// The dependency resolver the edition implies, which a virtual
+    /// workspace has to spell out.
+    pub fn resolver(self) -> &'static str {
+        match self {
+            Edition::E2015 | Edition::E2018 => "1",
+            Edition::E2021 => "2",
+            Edition::E2024 => "3",
+        }
+    }
+}
+
+impl fmt::Display for Edition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Edition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Edition::ALL
+            .into_iter()
+            .find(|edition| edition.as_str() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown edition `{}` (expected 2015, 2018, 2021 or 2024)",
+                    s
+                )
+            })
+    }
+}
+
+/// `(path, contents)` pairs matching what `cargo new <name>` writes: the
+/// manifest, `src/main.rs` or `src/lib.rs`, and `.gitignore` when a git
+/// repository is initialized.
+pub fn files(name: &str, bin: bool, edition: Edition, gitignore: bool) -> Vec<(String, String)> {
+    let manifest = format!(
+        "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"{}\"\n\n[dependencies]\n",
+        name, edition
+    );
+    let (source_path, source) = if bin {
+        ("src/main.rs", MAIN_RS)
+    } else {
+        ("src/lib.rs", LIB_RS)
+    };
+
+    let mut files = vec![
+        ("Cargo.toml".to_string(), manifest),
+        (source_path.to_string(), source.to_string()),
+    ];
+    if gitignore {
+        files.push((".gitignore".to_string(), "/target\n".to_string()));
+    }
+    files
+}
+
+/// The root Cargo.toml of a new virtual workspace with `members`, before
+/// any profile metadata is filled in.
+pub fn workspace_manifest(members: &[&str], edition: Edition) -> String {
+    let members: Vec<String> = members.iter().map(|m| format!("\"{}\"", m)).collect();
+    format!(
+        "[workspace]\nresolver = \"{}\"\nmembers = [{}]\n\n[workspace.package]\nversion = \"0.1.0\"\nedition = \"{}\"\n\n[workspace.dependencies]\n",
+        edition.resolver(),
+        members.join(", "),
+        edition
+    )
+}
+
+/// Check `name` the way `cargo new` does, returning why it is rejected.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let Some(first) = name.chars().next() else {
+        return Err("the name cannot be empty".into());
+    };
+    if first.is_ascii_digit() {
+        return Err("the name cannot start with a digit".into());
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+    {
+        return Err(format!(
+            "invalid character `{}`: only letters, numbers, `-` and `_` are allowed",
+            c
+        ));
+    }
+    if name == "test" {
+        return Err("it conflicts with Rust's built-in test library".into());
+    }
+    if KEYWORDS.contains(&name) {
+        return Err("the name is a reserved Rust keyword".into());
+    }
+    Ok(())
+}
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+const MAIN_RS: &str = r#"fn main() {
+    println!("Hello, world!");
+}
+"#;
+
+const LIB_RS: &str = r#"pub fn add(left: u64, right: u64) -> u64 {
+    left + right
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_works() {
+        let result = add(2, 2);
+        assert_eq!(result, 4);
+    }
+}
+"#;
@@ -0,0 +1,372 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::profile;
+
+/// Variables available to templates, e.g. `crate_name` or `year`.
+pub type Vars = BTreeMap<String, String>;
+
+/// Templates compiled into the binary, keyed by their output path.
+const BUILTIN: &[(&str, &str)] = &[
+    ("README.md", include_str!("templates/README.md")),
+    ("CHANGELOG.md", include_str!("templates/CHANGELOG.md")),
+    ("tests/basic.rs", include_str!("templates/tests/basic.rs")),
+    (
+        "benches/bench.rs",
+        include_str!("templates/benches/bench.rs"),
+    ),
+];
+
+/// The README for a workspace root, listing its members.
+const WORKSPACE_README: &str = include_str!("templates/workspace/README.md");
+
+/// A template file: its (unrendered) relative path and contents.
+pub struct Template {
+    pub path: String,
+    pub contents: String,
+}
+
+impl Template {
+    pub fn new(path: impl Into<String>, contents: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            contents: contents.into(),
+        }
+    }
+}
+
+/// The built-in README, CHANGELOG, tests/ and benches/ templates. The CI
+/// pipeline is generated by [`crate::ci`].
+pub fn builtin() -> Vec<Template> {
+    BUILTIN
+        .iter()
+        .map(|(path, contents)| Template::new(*path, *contents))
+        .collect()
+}
+
+/// The built-in templates for a workspace root: a README listing the
+/// members and the CHANGELOG shared by all of them.
+pub fn builtin_workspace() -> Vec<Template> {
+    let mut templates = vec![Template::new("README.md", WORKSPACE_README)];
+    templates.extend(
+        BUILTIN
+            .iter()
+            .filter(|(path, _)| *path == "CHANGELOG.md")
+            .map(|(path, contents)| Template::new(*path, *contents)),
+    );
+    templates
+}
+
+/// The default user template directory, `templates` next to the profile
+/// in `$XDG_CONFIG_HOME/cargo-setup` (`~/.config/cargo-setup`).
+pub fn default_dir() -> Option<PathBuf> {
+    profile::config_dir().map(|dir| dir.join("templates"))
+}
+
+/// Read every file under `dir`, recursively. Paths are relative to `dir`
+/// and always use `/` as separator.
+pub fn load_dir(dir: &Path) -> io::Result<Vec<Template>> {
+    let mut templates = Vec::new();
+    load_into(dir, dir, &mut templates)?;
+    templates.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(templates)
+}
+
+fn load_into(root: &Path, dir: &Path, templates: &mut Vec<Template>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            load_into(root, &path, templates)?;
+        } else {
+            let relative = path.strip_prefix(root).expect("path is inside root");
+            let components: Vec<_> = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            templates.push(Template::new(
+                components.join("/"),
+                fs::read_to_string(&path)?,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// A rendered file, ready to be written relative to the crate root.
+pub struct RenderedFile {
+    pub path: String,
+    pub contents: String,
+    /// Whether an existing file at `path` gets replaced. Built-in files are
+    /// only written when missing; files from a user template directory
+    /// always win, so they can replace what `cargo new` generated.
+    pub overwrite: bool,
+}
+
+/// Render `builtin` and `user` templates, paths included. A user template
+/// whose rendered path matches a built-in one replaces it.
+pub fn render_all(
+    builtin: &[Template],
+    user: &[Template],
+    vars: &Vars,
+) -> Result<Vec<RenderedFile>, TemplateError> {
+    let mut files: Vec<RenderedFile> = Vec::new();
+    for (templates, overwrite) in [(builtin, false), (user, true)] {
+        for template in templates {
+            let in_file = |err| TemplateError::InFile(template.path.clone(), Box::new(err));
+            let path = render(&template.path, vars).map_err(in_file)?;
+            if Path::new(&path)
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_)))
+            {
+                return Err(TemplateError::InvalidPath(path));
+            }
+            let file = RenderedFile {
+                contents: render(&template.contents, vars).map_err(in_file)?,
+                path,
+                overwrite,
+            };
+            match files.iter_mut().find(|f| f.path == file.path) {
+                Some(existing) => *existing = file,
+                None => files.push(file),
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Render `template`, replacing `{{name}}` with the matching variable and
+/// keeping `{{#if name}}...{{/if}}` blocks only when `name` is non-empty.
+///
+/// Placeholders that don't name a known variable are left untouched, so
+/// GitHub Actions expressions like `${{ matrix.os }}` pass through as-is.
+/// An `{{#if}}` or `{{/if}}` tag alone on its line doesn't leave a blank
+/// line behind.
+pub fn render(template: &str, vars: &Vars) -> Result<String, TemplateError> {
+    let mut out = String::with_capacity(template.len());
+    // One entry per open `{{#if}}`: whether its body is being emitted.
+    let mut conditions: Vec<bool> = Vec::new();
+    let mut rest = template;
+    let mut at_line_start = true;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let tag = rest[start + 2..start + len].trim();
+        let before = &rest[..start];
+        let after = &rest[start + len + 2..];
+        let emitting = conditions.iter().all(|&c| c);
+        if emitting {
+            out.push_str(before);
+        }
+
+        let block = if let Some(name) = tag.strip_prefix("#if ") {
+            conditions.push(vars.get(name.trim()).is_some_and(|v| !v.is_empty()));
+            true
+        } else if tag == "/if" {
+            conditions.pop().ok_or(TemplateError::UnexpectedEndIf)?;
+            true
+        } else {
+            if emitting {
+                match vars.get(tag) {
+                    Some(value) => out.push_str(value),
+                    None => out.push_str(&rest[start..start + len + 2]),
+                }
+            }
+            false
+        };
+
+        let indent = &before[before.rfind('\n').map_or(0, |i| i + 1)..];
+        let standalone = block
+            && (at_line_start || before.contains('\n'))
+            && indent.chars().all(|c| c == ' ' || c == '\t')
+            && after.starts_with('\n');
+        if standalone {
+            if emitting {
+                out.truncate(out.len() - indent.len());
+            }
+            rest = &after[1..];
+        } else {
+            rest = after;
+        }
+        at_line_start = standalone;
+    }
+
+    if !conditions.is_empty() {
+        return Err(TemplateError::UnclosedIf);
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// A template that couldn't be rendered.
+#[derive(Debug)]
+pub enum TemplateError {
+    UnclosedIf,
+    UnexpectedEndIf,
+    /// A rendered file name that would escape the crate directory.
+    InvalidPath(String),
+    /// An error in the named template file.
+    InFile(String, Box<TemplateError>),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::UnclosedIf => write!(f, "`{{{{#if}}}}` block is never closed"),
+            TemplateError::UnexpectedEndIf => {
+                write!(f, "`{{{{/if}}}}` without a matching `{{{{#if}}}}`")
+            }
+            TemplateError::InvalidPath(path) => {
+                write!(f, "`{}` is not a path inside the crate", path)
+            }
+            TemplateError::InFile(path, err) => write!(f, "{}: {}", path, err),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vars {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn rendered(template: &str, pairs: &[(&str, &str)]) -> String {
+        render(template, &vars(pairs)).unwrap()
+    }
+
+    #[test]
+    fn replaces_known_variables() {
+        assert_eq!(
+            rendered(
+                "# {{crate_name}} ({{ year }})\n",
+                &[("crate_name", "x"), ("year", "2026")]
+            ),
+            "# x (2026)\n"
+        );
+    }
+
+    #[test]
+    fn leaves_unknown_placeholders_alone() {
+        let template = "os: ${{ matrix.os }}\nname: {{crate_name}}\n";
+        assert_eq!(
+            rendered(template, &[("crate_name", "x")]),
+            "os: ${{ matrix.os }}\nname: x\n"
+        );
+        assert_eq!(rendered("a {{ b", &[]), "a {{ b");
+    }
+
+    #[test]
+    fn keeps_if_blocks_only_for_non_empty_variables() {
+        let template = "a{{#if x}}b{{/if}}c";
+        assert_eq!(rendered(template, &[("x", "1")]), "abc");
+        assert_eq!(rendered(template, &[("x", "")]), "ac");
+        assert_eq!(rendered(template, &[]), "ac");
+        let nested = "{{#if x}}1{{#if y}}2{{/if}}3{{/if}}";
+        assert_eq!(rendered(nested, &[("x", "1")]), "13");
+        assert_eq!(rendered(nested, &[("x", "1"), ("y", "1")]), "123");
+        assert_eq!(rendered(nested, &[("y", "1")]), "");
+    }
+
+    #[test]
+    fn standalone_tags_leave_no_blank_lines() {
+        let template = "a\n  {{#if x}}\n  b\n  {{/if}}\nc\n";
+        assert_eq!(rendered(template, &[("x", "1")]), "a\n  b\nc\n");
+        assert_eq!(rendered(template, &[]), "a\nc\n");
+        // At the very start of the template too
+        assert_eq!(rendered("{{#if x}}\nb\n{{/if}}\nc\n", &[]), "c\n");
+        // Tags sharing their line with text keep the line
+        assert_eq!(
+            rendered("a {{#if x}}b{{/if}}\nc\n", &[("x", "1")]),
+            "a b\nc\n"
+        );
+        assert_eq!(rendered("{{#if x}}b{{/if}}\nc\n", &[]), "\nc\n");
+    }
+
+    #[test]
+    fn rejects_unbalanced_if_blocks() {
+        assert!(matches!(
+            render("{{#if x}}a", &Vars::new()),
+            Err(TemplateError::UnclosedIf)
+        ));
+        assert!(matches!(
+            render("a{{/if}}", &Vars::new()),
+            Err(TemplateError::UnexpectedEndIf)
+        ));
+    }
+
+    #[test]
+    fn renders_paths_and_lets_user_templates_win() {
+        let builtin = [
+            Template::new("README.md", "built-in"),
+            Template::new("CHANGELOG.md", "changes"),
+        ];
+        let user = [
+            Template::new("README.md", "mine"),
+            Template::new("src/{{crate_ident}}.rs", "// {{crate_name}}"),
+        ];
+        let vars = vars(&[("crate_name", "my-crate"), ("crate_ident", "my_crate")]);
+        let files = render_all(&builtin, &user, &vars).unwrap();
+        let files: Vec<_> = files
+            .iter()
+            .map(|file| (file.path.as_str(), file.contents.as_str(), file.overwrite))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                ("README.md", "mine", true),
+                ("CHANGELOG.md", "changes", false),
+                ("src/my_crate.rs", "// my-crate", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_paths_outside_the_crate() {
+        for path in ["../x", "a/../../x", "/etc/x", "{{up}}/x", "./x"] {
+            let templates = [Template::new(path, "")];
+            let err = render_all(&[], &templates, &vars(&[("up", "..")]))
+                .err()
+                .unwrap();
+            assert!(
+                matches!(err, TemplateError::InvalidPath(_)),
+                "{}: {}",
+                path,
+                err
+            );
+        }
+        let templates = [Template::new("{{#if x}}", "")];
+        let err = render_all(&[], &templates, &Vars::new()).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "{{#if x}}: `{{#if}}` block is never closed"
+        );
+    }
+
+    #[test]
+    fn loads_nested_directories_with_slash_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/bin")).unwrap();
+        fs::write(dir.path().join("README.md"), "readme").unwrap();
+        fs::write(dir.path().join("src/bin/tool.rs"), "tool").unwrap();
+        let templates = load_dir(dir.path()).unwrap();
+        let templates: Vec<_> = templates
+            .iter()
+            .map(|t| (t.path.as_str(), t.contents.as_str()))
+            .collect();
+        assert_eq!(
+            templates,
+            [("README.md", "readme"), ("src/bin/tool.rs", "tool")]
+        );
+    }
+}
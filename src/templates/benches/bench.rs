@@ -0,0 +1,4 @@
+// Basic benchmark (requires criterion)
+fn main() {
+    println!("Run with cargo bench");
+}
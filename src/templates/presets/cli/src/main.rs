@@ -0,0 +1,15 @@
+use clap::Parser;
+
+/// {{crate_name}} command-line interface
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Cli {
+    /// Who to greet
+    #[arg(short, long, default_value = "world")]
+    name: String,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    println!("Hello, {}!", cli.name);
+}
@@ -0,0 +1,11 @@
+use std::process::Command;
+
+#[test]
+fn greets() {
+    let output = Command::new(env!("CARGO_BIN_EXE_{{crate_name}}"))
+        .args(["--name", "tests"])
+        .output()
+        .expect("failed to run the binary");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Hello, tests!\n");
+}
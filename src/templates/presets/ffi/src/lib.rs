@@ -0,0 +1,8 @@
+//! C-compatible API. Generate the header with
+//! `cbindgen --config cbindgen.toml --output include/{{crate_ident}}.h`.
+
+/// Adds two numbers.
+#[unsafe(no_mangle)]
+pub extern "C" fn {{crate_ident}}_add(left: u64, right: u64) -> u64 {
+    left + right
+}
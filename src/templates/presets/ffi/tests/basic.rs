@@ -0,0 +1,4 @@
+#[test]
+fn adds() {
+    assert_eq!({{crate_ident}}::{{crate_ident}}_add(2, 2), 4);
+}
@@ -0,0 +1,4 @@
+#[test]
+fn it_works() {
+    assert_eq!({{crate_ident}}::add(2, 2), 4);
+}
@@ -0,0 +1,9 @@
+use {{crate_ident}}::Hello;
+
+#[derive(Hello)]
+struct Greeter;
+
+#[test]
+fn derives_hello() {
+    assert_eq!(Greeter::hello(), "Greeter");
+}
@@ -0,0 +1,18 @@
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() {
+    println!("{{crate_name}} starting");
+    tokio::select! {
+        _ = run() => {}
+        _ = tokio::signal::ctrl_c() => println!("shutting down"),
+    }
+}
+
+async fn run() {
+    let mut interval = tokio::time::interval(Duration::from_secs(5));
+    loop {
+        interval.tick().await;
+        println!("tick");
+    }
+}
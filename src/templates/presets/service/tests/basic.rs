@@ -0,0 +1,5 @@
+#[tokio::test]
+async fn runtime_works() {
+    let handle = tokio::spawn(async { 2 + 2 });
+    assert_eq!(handle.await.unwrap(), 4);
+}
@@ -0,0 +1,222 @@
+//! All-or-nothing writes, so a failure never leaves a half-scaffolded crate.
+//!
+//! New crates are generated in a staging directory next to the target and
+//! renamed into place once every step succeeded. Changes to an existing
+//! crate go through a [`Transaction`], which puts back the original files if
+//! it is dropped before [`Transaction::commit`] — including while unwinding
+//! from a panic.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use tempfile::{NamedTempFile, TempDir};
+
+/// Create an empty staging directory in the same parent as `target`, so the
+/// final rename stays on one filesystem. It is deleted when dropped. Like
+/// `cargo new`, this creates the missing parent directories.
+pub fn staging_dir(target: &Path) -> io::Result<TempDir> {
+    let parent = match target.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(parent)?;
+    tempfile::Builder::new()
+        .prefix(".cargo-setup-")
+        .tempdir_in(parent)
+}
+
+/// Move the fully generated crate at `staged` to `target`.
+pub fn commit_dir(staged: &Path, target: &Path) -> io::Result<()> {
+    if target.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("destination `{}` already exists", target.display()),
+        ));
+    }
+    fs::rename(staged, target)
+}
+
+/// A set of file writes under `root` that is rolled back unless committed.
+pub struct Transaction {
+    root: PathBuf,
+    /// Files that didn't exist before, in creation order.
+    created_files: Vec<PathBuf>,
+    /// Directories that didn't exist before, in creation order.
+    created_dirs: Vec<PathBuf>,
+    /// Original contents of files that were replaced.
+    backups: Vec<(PathBuf, Vec<u8>)>,
+    committed: bool,
+}
+
+impl Transaction {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            created_files: Vec::new(),
+            created_dirs: Vec::new(),
+            backups: Vec::new(),
+            committed: false,
+        }
+    }
+
+    /// Write `contents` to `path` (relative to the root). Each file is
+    /// written to a temporary file first and renamed over the target, so a
+    /// single file is never left half-written.
+    pub fn write(&mut self, path: &str, contents: &[u8]) -> io::Result<()> {
+        let path = self.root.join(path);
+        let parent = path.parent().unwrap_or(&self.root).to_path_buf();
+        self.create_dirs(&parent)?;
+
+        let seen = self.created_files.contains(&path)
+            || self.backups.iter().any(|(backup, _)| *backup == path);
+        match fs::read(&path) {
+            _ if seen => {}
+            Ok(original) => self.backups.push((path.clone(), original)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                self.created_files.push(path.clone())
+            }
+            Err(err) => return Err(err),
+        }
+
+        let mut staged = NamedTempFile::new_in(&parent)?;
+        staged.write_all(contents)?;
+        staged.persist(&path).map_err(|err| err.error)?;
+        Ok(())
+    }
+
+    /// Keep every write.
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+
+    fn create_dirs(&mut self, dir: &Path) -> io::Result<()> {
+        let mut missing = Vec::new();
+        let mut current = Some(dir);
+        while let Some(dir) = current {
+            if dir.exists() {
+                break;
+            }
+            missing.push(dir.to_path_buf());
+            current = dir.parent();
+        }
+        for dir in missing.into_iter().rev() {
+            fs::create_dir(&dir)?;
+            self.created_dirs.push(dir);
+        }
+        Ok(())
+    }
+
+    fn rollback(&mut self) {
+        // Best effort: keep going so as much as possible is restored.
+        for (path, original) in self.backups.drain(..).rev() {
+            let _ = fs::write(path, original);
+        }
+        for path in self.created_files.drain(..).rev() {
+            let _ = fs::remove_file(path);
+        }
+        for dir in self.created_dirs.drain(..).rev() {
+            let _ = fs::remove_dir(dir);
+        }
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.rollback();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolls_back_unless_committed() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("README.md"), "mine").unwrap();
+        fs::create_dir(root.join("src")).unwrap();
+
+        let mut transaction = Transaction::new(root);
+        transaction.write("README.md", b"generated").unwrap();
+        transaction.write("README.md", b"generated twice").unwrap();
+        transaction.write("src/lib.rs", b"// new").unwrap();
+        transaction
+            .write(".github/workflows/ci.yml", b"on: push")
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("README.md")).unwrap(),
+            "generated twice"
+        );
+        assert!(root.join(".github/workflows/ci.yml").exists());
+        drop(transaction);
+
+        assert_eq!(fs::read_to_string(root.join("README.md")).unwrap(), "mine");
+        assert!(!root.join("src/lib.rs").exists());
+        assert!(root.join("src").is_dir());
+        assert!(!root.join(".github").exists());
+    }
+
+    #[test]
+    fn keeps_committed_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("README.md"), "mine").unwrap();
+
+        let mut transaction = Transaction::new(root);
+        transaction.write("README.md", b"generated").unwrap();
+        transaction.write("tests/basic.rs", b"#[test]").unwrap();
+        transaction.commit();
+
+        assert_eq!(
+            fs::read_to_string(root.join("README.md")).unwrap(),
+            "generated"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("tests/basic.rs")).unwrap(),
+            "#[test]"
+        );
+    }
+
+    #[test]
+    fn rolls_back_while_unwinding() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        let result = std::panic::catch_unwind(|| {
+            let mut transaction = Transaction::new(&root);
+            transaction.write("LICENSE", b"MIT").unwrap();
+            panic!("a later step failed");
+        });
+        assert!(result.is_err());
+        assert!(!root.join("LICENSE").exists());
+    }
+
+    #[test]
+    fn commit_dir_refuses_an_existing_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("crates/mycrate");
+        let staging = staging_dir(&target).unwrap();
+        assert!(dir.path().join("crates").is_dir());
+        let staged = staging.path().join("mycrate");
+        fs::create_dir(&staged).unwrap();
+        fs::write(staged.join("Cargo.toml"), "[package]").unwrap();
+
+        fs::create_dir(&target).unwrap();
+        let err = commit_dir(&staged, &target).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(staged.join("Cargo.toml").exists());
+        assert!(fs::read_dir(&target).unwrap().next().is_none());
+
+        fs::remove_dir(&target).unwrap();
+        commit_dir(&staged, &target).unwrap();
+        assert_eq!(
+            fs::read_to_string(target.join("Cargo.toml")).unwrap(),
+            "[package]"
+        );
+        drop(staging);
+        assert_eq!(fs::read_dir(dir.path().join("crates")).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,83 @@
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Version control to initialize in a new crate, like `cargo new --vcs`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Vcs {
+    #[default]
+    Git,
+    None,
+}
+
+impl Vcs {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Vcs::Git => "git",
+            Vcs::None => "none",
+        }
+    }
+
+    /// What actually happens for a crate at `path`: like `cargo new`, no
+    /// repository is created inside an existing git work tree.
+    pub fn effective(self, path: &Path) -> Vcs {
+        match self {
+            Vcs::Git if in_git_repository(path) => Vcs::None,
+            vcs => vcs,
+        }
+    }
+}
+
+impl FromStr for Vcs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "git" => Ok(Vcs::Git),
+            "none" => Ok(Vcs::None),
+            _ => Err(format!("unknown VCS `{}` (expected `git` or `none`)", s)),
+        }
+    }
+}
+
+/// The value of `git config <key>` as seen from `dir`, or `None` if it is
+/// unset or git can't be run.
+pub fn git_config(dir: &Path, key: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["config", "--get", key])
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    let value = String::from_utf8(output.stdout).ok()?;
+    let value = value.trim();
+    (output.status.success() && !value.is_empty()).then(|| value.to_string())
+}
+
+/// Whether `path` (which may not exist yet) is inside a git work tree.
+fn in_git_repository(path: &Path) -> bool {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    path.ancestors().any(|dir| dir.join(".git").exists())
+}
+
+/// Run `git init` in `dir`.
+pub fn init_git(dir: &Path) -> Result<(), Error> {
+    let command = "git init".to_string();
+    let status = Command::new("git")
+        .arg("init")
+        .arg("--quiet")
+        .arg(dir)
+        .status()
+        .map_err(|source| Error::Spawn {
+            command: command.clone(),
+            source,
+        })?;
+    if !status.success() {
+        return Err(Error::CommandFailed { command, status });
+    }
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! Finding the Cargo workspace a new crate lands in.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::manifest::{self, WorkspaceInfo};
+
+/// Package fields a member inherits with `key.workspace = true` when the
+/// workspace defines them, as `cargo new` does.
+pub const INHERITED_KEYS: &[&str] = &[
+    "version",
+    "edition",
+    "authors",
+    "description",
+    "documentation",
+    "readme",
+    "homepage",
+    "repository",
+    "license",
+    "license-file",
+    "keywords",
+    "categories",
+    "publish",
+    "rust-version",
+    "include",
+    "exclude",
+];
+
+/// The workspace enclosing a new crate.
+#[derive(Clone, Debug)]
+pub struct Workspace {
+    /// The directory holding the root Cargo.toml.
+    pub root: PathBuf,
+    /// The root Cargo.toml as it is now.
+    pub manifest: String,
+    /// The crate's path relative to `root`, `/`-separated, as it appears
+    /// in `members`.
+    pub member: String,
+    pub info: WorkspaceInfo,
+}
+
+impl Workspace {
+    /// Look for the workspace that would own a crate at `crate_path`: like
+    /// Cargo, the closest parent directory whose Cargo.toml has a
+    /// `[workspace]` table, unless it excludes the crate.
+    pub fn find(crate_path: &Path) -> Result<Option<Workspace>> {
+        let crate_path =
+            normalize(&std::path::absolute(crate_path).map_err(|err| Error::io(crate_path, err))?);
+        for dir in crate_path.ancestors().skip(1) {
+            let manifest_path = dir.join("Cargo.toml");
+            let manifest = match fs::read_to_string(&manifest_path) {
+                Ok(manifest) => manifest,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(Error::io(&manifest_path, err)),
+            };
+            let info = manifest::workspace_info(&manifest).map_err(|err| Error::Manifest {
+                path: manifest_path.clone(),
+                message: err.to_string(),
+            })?;
+            let Some(info) = info else {
+                continue;
+            };
+
+            let member = relative(&crate_path, dir);
+            let excluded = info.exclude.iter().any(|path| {
+                let path = path.trim_end_matches('/');
+                member == path || member.starts_with(&format!("{}/", path))
+            });
+            if excluded {
+                return Ok(None);
+            }
+            return Ok(Some(Workspace {
+                root: dir.to_path_buf(),
+                manifest,
+                member,
+                info,
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Whether `members` already covers the crate, directly or by a glob
+    /// such as `crates/*`.
+    pub fn is_member(&self) -> bool {
+        self.info
+            .members
+            .iter()
+            .any(|pattern| matches(pattern, &self.member))
+    }
+
+    /// The keys from [`INHERITED_KEYS`] that `[workspace.package]` defines.
+    pub fn inherited_keys(&self) -> Vec<&'static str> {
+        INHERITED_KEYS
+            .iter()
+            .copied()
+            .filter(|key| self.info.package_keys.iter().any(|k| k == key))
+            .collect()
+    }
+}
+
+/// Resolve `.` and `..` in an absolute path without touching the disk (the
+/// crate directory doesn't exist yet).
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// `path` relative to `base` with `/` separators; `path` must be inside
+/// `base`.
+fn relative(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base)
+        .expect("the workspace root is an ancestor")
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Match a `members` entry against a `/`-separated path. Each
+/// segment may use `*` and `?` wildcards, like the globs Cargo accepts.
+fn matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.trim_end_matches('/').split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    pattern.len() == path.len()
+        && pattern
+            .iter()
+            .zip(&path)
+            .all(|(pattern, part)| matches_segment(pattern.as_bytes(), part.as_bytes()))
+}
+
+fn matches_segment(pattern: &[u8], text: &[u8]) -> bool {
+    match (pattern.first(), text.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            matches_segment(&pattern[1..], text)
+                || (!text.is_empty() && matches_segment(pattern, &text[1..]))
+        }
+        (Some(b'?'), Some(_)) => matches_segment(&pattern[1..], &text[1..]),
+        (Some(p), Some(t)) if p == t => matches_segment(&pattern[1..], &text[1..]),
+        _ => false,
+    }
+}
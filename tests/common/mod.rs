@@ -0,0 +1,45 @@
+//! Running the `cargo-setup` binary from the integration tests.
+
+// Each test file uses only some of these
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// Run `cargo setup <args>` in `dir`, away from the user's profile, git
+/// identity and `CARGO_SETUP_*` settings.
+pub fn cargo_setup(dir: &Path, args: &[&str]) -> Output {
+    let home = dir.join(".home");
+    let mut command = Command::new(env!("CARGO_BIN_EXE_cargo-setup"));
+    command
+        .arg("setup")
+        .args(args)
+        .current_dir(dir)
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_CONFIG_GLOBAL", home.join(".gitconfig"));
+    for (key, _) in std::env::vars_os() {
+        if key.to_string_lossy().starts_with("CARGO_SETUP_") {
+            command.env_remove(key);
+        }
+    }
+    command.output().expect("cargo-setup runs")
+}
+
+pub fn success(output: Output) -> String {
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    assert!(
+        output.status.success(),
+        "failed with {}:\n{}{}",
+        output.status,
+        stdout,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    stdout
+}
+
+pub fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap()
+}
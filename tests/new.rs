@@ -0,0 +1,91 @@
+//! `cargo setup <name>` end to end.
+
+mod common;
+
+use std::fs;
+
+use common::{cargo_setup, read, success};
+use tempfile::TempDir;
+
+#[test]
+fn a_path_names_the_crate_after_its_last_component() {
+    let dir = TempDir::new().unwrap();
+    let stdout = success(cargo_setup(dir.path(), &["crates/newc", "--vcs", "none"]));
+    assert!(stdout.contains("project `newc`"), "{}", stdout);
+
+    let manifest = read(&dir.path().join("crates/newc/Cargo.toml"));
+    assert!(manifest.contains("name = \"newc\""), "{}", manifest);
+    assert!(dir.path().join("crates/newc/src/lib.rs").exists());
+}
+
+#[test]
+fn dry_run_shows_the_new_manifest() {
+    let dir = TempDir::new().unwrap();
+    let stdout = success(cargo_setup(dir.path(), &["newc", "--dry-run"]));
+    assert!(
+        stdout.contains("── Cargo.toml (new) ──\n[package]\nname = \"newc\"\n"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("(unchanged)"), "{}", stdout);
+    assert!(!dir.path().join("newc").exists());
+}
+
+#[test]
+fn a_path_without_a_final_name_is_rejected() {
+    let dir = TempDir::new().unwrap();
+    let output = cargo_setup(dir.path(), &["..", "--vcs", "none"]);
+    assert_eq!(output.status.code(), Some(10));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("the path doesn't end in a directory name"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn a_member_path_from_the_workspace_root_joins_the_workspace() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("ws");
+    fs::create_dir(&root).unwrap();
+    fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\n\
+         resolver = \"2\"\n\
+         # Our crates\n\
+         members = [\n    \"a\",\n    \"b\",\n]\n\
+         \n\
+         [workspace.package]\n\
+         edition = \"2021\"\n\
+         license = \"MIT\"\n",
+    )
+    .unwrap();
+
+    let args = ["crates/newc", "--vcs", "none"];
+    let stdout = success(cargo_setup(&root, &[&args[..], &["--dry-run"]].concat()));
+    assert!(stdout.contains("+    \"crates/newc\","), "{}", stdout);
+    assert!(!root.join("crates").exists());
+
+    let stdout = success(cargo_setup(&root, &args));
+    assert!(stdout.contains("member of the workspace"), "{}", stdout);
+
+    let workspace = read(&root.join("Cargo.toml"));
+    assert!(
+        workspace.contains(
+            "# Our crates\nmembers = [\n    \"a\",\n    \"b\",\n    \"crates/newc\",\n]\n"
+        ),
+        "{}",
+        workspace
+    );
+    let manifest = read(&root.join("crates/newc/Cargo.toml"));
+    for line in [
+        "name = \"newc\"",
+        "edition.workspace = true",
+        "license.workspace = true",
+    ] {
+        assert!(manifest.contains(line), "no `{}` in:\n{}", line, manifest);
+    }
+    assert!(!manifest.contains("edition = "), "{}", manifest);
+    assert!(!root.join("crates/newc/LICENSE").exists());
+}
@@ -0,0 +1,44 @@
+//! Every preset scaffolds a crate that passes its own CI's `cargo fmt`.
+
+mod common;
+
+use std::process::Command;
+
+use common::{cargo_setup, success};
+use tempfile::TempDir;
+
+#[test]
+fn generated_crates_are_formatted() {
+    let dir = TempDir::new().unwrap();
+    let kinds = [
+        &["--lib"][..],
+        &["--bin"],
+        &["--preset", "cli"],
+        &["--preset", "service"],
+        &["--preset", "proc-macro"],
+        &["--preset", "no_std"],
+        &["--preset", "ffi"],
+    ];
+    for (i, kind) in kinds.iter().enumerate() {
+        for edition in ["2018", "2021", "2024"] {
+            let name = format!("crate{}-{}", i, edition);
+            let mut args = vec![name.as_str(), "--vcs", "none", "--msrv", "1.85"];
+            args.extend(["--edition", edition]);
+            args.extend(*kind);
+            success(cargo_setup(dir.path(), &args));
+
+            let output = Command::new("cargo")
+                .args(["fmt", "--check"])
+                .current_dir(dir.path().join(&name))
+                .output()
+                .expect("cargo fmt runs");
+            assert!(
+                output.status.success(),
+                "{} {}:\n{}",
+                kind.join(" "),
+                edition,
+                String::from_utf8_lossy(&output.stdout)
+            );
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! `cargo setup update` end to end: create a crate, edit it, update it.
+
+mod common;
+
+use std::fs;
+
+use common::{cargo_setup, read, success};
+use tempfile::TempDir;
+
+/// A crate created with options that all change the generated files.
+fn create() -> (TempDir, std::path::PathBuf) {
+    let dir = TempDir::new().unwrap();
+    success(cargo_setup(
+        dir.path(),
+        &[
+            "mytool",
+            "--bin",
+            "--vcs",
+            "none",
+            "--ci-jobs",
+            "coverage,deny",
+            "--msrv",
+            "1.85",
+            "--release",
+            "--badges",
+            "ci,crates-io",
+            "--keywords",
+            "cli,tool",
+        ],
+    ));
+    let crate_dir = dir.path().join("mytool");
+    (dir, crate_dir)
+}
+
+#[test]
+fn update_without_changes_writes_nothing() {
+    let (_dir, crate_dir) = create();
+    let paths = [
+        "README.md",
+        "Cargo.toml",
+        "LICENSE",
+        "deny.toml",
+        ".github/workflows/ci.yml",
+        ".github/workflows/release.yml",
+        ".cargo-setup.lock",
+    ];
+    let before: Vec<String> = paths
+        .iter()
+        .map(|path| read(&crate_dir.join(path)))
+        .collect();
+
+    let stdout = success(cargo_setup(&crate_dir, &["update"]));
+    assert!(stdout.contains("0 written, 0 skipped"), "{}", stdout);
+    for (path, contents) in paths.iter().zip(before) {
+        assert_eq!(read(&crate_dir.join(path)), contents, "{} changed", path);
+    }
+}
+
+#[test]
+fn update_merges_edits_cleanly() {
+    let (_dir, crate_dir) = create();
+    let readme = crate_dir.join("README.md");
+    fs::write(&readme, read(&readme) + "\n## Notes\n\nOur own notes.\n").unwrap();
+
+    let stdout = success(cargo_setup(
+        &crate_dir,
+        &[
+            "update",
+            "--description",
+            "Does one thing well",
+            "--ci-jobs",
+            "docs",
+        ],
+    ));
+    assert!(stdout.contains("updated  README.md"), "{}", stdout);
+
+    let readme = read(&readme);
+    assert!(readme.contains("\nDoes one thing well\n"), "{}", readme);
+    assert!(
+        readme.ends_with("## Notes\n\nOur own notes.\n"),
+        "{}",
+        readme
+    );
+    assert!(!readme.contains("<<<<<<<"), "{}", readme);
+    let ci = read(&crate_dir.join(".github/workflows/ci.yml"));
+    assert!(ci.contains("cargo doc"), "{}", ci);
+    assert!(!ci.contains("llvm-cov"), "{}", ci);
+    assert!(
+        stdout.contains("skipped  deny.toml (no longer generated)"),
+        "{}",
+        stdout
+    );
+
+    // The lock keeps the generated README, not the merged one
+    let lock = read(&crate_dir.join(".cargo-setup.lock"));
+    assert!(lock.contains("Does one thing well"), "{}", lock);
+    assert!(!lock.contains("Our own notes"), "{}", lock);
+}
+
+#[test]
+fn update_writes_conflict_markers() {
+    let (_dir, crate_dir) = create();
+    let readme = crate_dir.join("README.md");
+    let edited: String = read(&readme)
+        .lines()
+        .map(|line| match line.starts_with("[![CI]") {
+            true => "Our own badge\n".to_string(),
+            false => format!("{}\n", line),
+        })
+        .collect();
+    fs::write(&readme, edited).unwrap();
+
+    let output = cargo_setup(&crate_dir, &["update", "--badges", "ci,license"]);
+    assert_eq!(output.status.code(), Some(14));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("conflict README.md"), "{}", stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("README.md"), "{}", stderr);
+
+    let readme = read(&readme);
+    let markers = [
+        "<<<<<<< current\nOur own badge\n",
+        "=======\n[![CI]",
+        "[![License](https://img.shields.io/badge/license-MIT-blue.svg)](LICENSE)\n>>>>>>> cargo setup update\n",
+    ];
+    for marker in markers {
+        assert!(readme.contains(marker), "no `{}` in:\n{}", marker, readme);
+    }
+}